@@ -1,42 +1,47 @@
+use std::env;
 use std::path::Path;
-use protoc_rust::Customize;
 
-extern crate protoc_rust;
+extern crate rustc_version;
+
+// Shared with the build scripts of user functions as cloudstate::build, behind the build
+// feature, of which only the descriptor set is needed here
+#[allow(dead_code)]
+#[path = "src/build.rs"]
+mod build;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // The generated code is only included, and rustfmt trips over anything else living in OUT_DIR
     tonic_build::configure()
+        .format(false)
         //.out_dir(Path::new("src/protos"))
         .compile(
             &[
                 "proto/google/proto/empty.proto",
                 "proto/google/proto/any.proto",
                 "proto/google/proto/descriptor.proto",
-                "proto/protocol/cloudstate/entity.proto"/*,
-                "proto/protocol/cloudstate/crdt.proto",
+                "proto/protocol/cloudstate/entity.proto",
                 "proto/protocol/cloudstate/event_sourced.proto",
-                "proto/protocol/cloudstate/function.proto"*/
+                "proto/protocol/cloudstate/crdt.proto",
+                "proto/protocol/cloudstate/function.proto",
+                "proto/grpc/health/v1/health.proto",
+                "proto/proxy/grpc/reflection/v1alpha/reflection.proto"
                 ],
-            &["proto"],
+            // The protocol imports cloudstate/entity.proto, resolve it to the file compiled above
+            &["proto/protocol", "proto"],
         )?;
 
-    protoc_rust::run(protoc_rust::Args {
-        out_dir: "src/protos",
-        input: &["proto/example/shoppingcart/shoppingcart.proto", "proto/example/shoppingcart/persistence/domain.proto"],
-        includes: &["proto"],
-        customize: Customize {
-            ..Default::default()
-        },
-    }).expect("protoc");
+    // Descriptor set of the shopping cart example, the user function of the unit tests
+    build::descriptor_set(
+        &["proto/example/shoppingcart/shoppingcart.proto", "proto/example/shoppingcart/persistence/domain.proto"],
+        &["proto"],
+        Path::new(&env::var("OUT_DIR")?).join("user-function.desc"),
+    )?;
 
-    /*protobuf_codegen_pure::run(protobuf_codegen_pure::Args {
-        out_dir: "src/protos",
-        input: &["proto/example/shoppingcart/shoppingcart.proto", "proto/example/shoppingcart/persistence/domain.proto"],
-        includes: &["proto"],
-        customize: protobuf_codegen_pure::Customize {
-            ..Default::default()
-        },
-    }).expect("protoc");*/
+    println!("cargo:rerun-if-changed=proto");
+    println!("cargo:rerun-if-changed=src/build.rs");
 
+    // Reported to the proxy on discovery as the runtime of the user function
+    println!("cargo:rustc-env=CLOUDSTATE_RUSTC_VERSION={}", rustc_version::version()?);
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,27 @@
+use std::env;
+use std::path::Path;
+
+// The protos live with the ones of the cloudstate crate, they import cloudstate/entity_key.proto
+const PROTOS: &[&str] = &[
+    "../../proto/example/shoppingcart/shoppingcart.proto",
+    "../../proto/example/shoppingcart/persistence/domain.proto",
+];
+const INCLUDES: &[&str] = &["../../proto"];
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Only the messages, the service is served through the CloudState protocol
+    tonic_build::configure()
+        .build_client(false)
+        .build_server(false)
+        .format(false)
+        .compile(PROTOS, INCLUDES)?;
+
+    // Sent to the proxy on discovery, and naming the messages in their Any payloads
+    let out_dir = env::var("OUT_DIR")?;
+    let descriptor_set = Path::new(&out_dir).join("user-function.desc");
+    cloudstate::build::descriptor_set(PROTOS, INCLUDES, &descriptor_set)?;
+    cloudstate::build::message_names(&descriptor_set, &out_dir)?;
+
+    println!("cargo:rerun-if-changed=../../proto/example");
+    Ok(())
+}
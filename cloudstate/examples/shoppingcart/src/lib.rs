@@ -0,0 +1,150 @@
+//! The shopping cart of the CloudState TCK, as an event sourced entity.
+
+use prost_types::Any;
+use cloudstate::handlers::handler::{CommandContext, EventSourcedEntity, Payload};
+use cloudstate::payload::{self, CommandRegistry, PayloadError, TypeRegistry};
+use cloudstate::protocol::spec::{Failure, Reply};
+use cloudstate::serveless::EntityService;
+
+pub mod shoppingcart {
+    include!(concat!(env!("OUT_DIR"), "/com.example.shoppingcart.rs"));
+    cloudstate::include_message_names!("com.example.shoppingcart");
+
+    pub mod persistence {
+        include!(concat!(env!("OUT_DIR"), "/com.example.shoppingcart.persistence.rs"));
+        cloudstate::include_message_names!("com.example.shoppingcart.persistence");
+    }
+}
+
+use shoppingcart::{AddLineItem, GetShoppingCart, LineItem, RemoveLineItem};
+use shoppingcart::persistence::{self as domain, ItemAdded, ItemRemoved};
+
+pub const SERVICE_NAME: &str = "com.example.shoppingcart.ShoppingCart";
+
+/// Descriptor set of the shopping cart protos, sent to the proxy on discovery.
+pub const DESCRIPTOR: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/user-function.desc"));
+
+/// The shopping cart service, snapshotted every 5 events.
+pub fn service() -> EntityService {
+    EntityService::new()
+        .persistence_id("shopping-cart".to_string())
+        .snapshot(5)
+        .entity(ShoppingCart)
+        .event_sourced()
+}
+
+pub enum Command {
+    AddItem(AddLineItem),
+    RemoveItem(RemoveLineItem),
+    GetCart(GetShoppingCart),
+}
+
+pub enum Event {
+    ItemAdded(ItemAdded),
+    ItemRemoved(ItemRemoved),
+}
+
+impl Payload for Event {
+
+    fn to_any(&self) -> Any {
+        match self {
+            Event::ItemAdded(event) => payload::pack(event),
+            Event::ItemRemoved(event) => payload::pack(event),
+        }
+    }
+}
+
+/// The items of a cart, in the order they were first added.
+#[derive(Default)]
+pub struct Cart {
+    items: Vec<domain::LineItem>,
+}
+
+impl Cart {
+
+    fn item(&self, product_id: &str) -> Option<&domain::LineItem> {
+        self.items.iter().find(|item| item.product_id == product_id)
+    }
+}
+
+pub struct ShoppingCart;
+
+impl EventSourcedEntity for ShoppingCart {
+    type State = Cart;
+    type Command = Command;
+    type Event = Event;
+
+    fn register_types(&self, commands: &mut CommandRegistry<Command>, events: &mut TypeRegistry<Event>) {
+        commands
+            .register("AddItem", Command::AddItem)
+            .register("RemoveItem", Command::RemoveItem)
+            .register("GetCart", Command::GetCart);
+        events
+            .register(Event::ItemAdded)
+            .register(Event::ItemRemoved);
+    }
+
+    fn handle_command(&self, cart: &Cart, command: Command, ctx: &mut CommandContext<Event>) -> Result<Reply, Failure> {
+        match command {
+            Command::AddItem(item) => {
+                if item.quantity <= 0 {
+                    return Err(ctx.fail(format!("Cannot add negative quantity of to item {}", item.product_id)));
+                }
+
+                ctx.emit(Event::ItemAdded(ItemAdded {
+                    item: Some(domain::LineItem {
+                        product_id: item.product_id,
+                        name: item.name,
+                        quantity: item.quantity,
+                    }),
+                }));
+                Ok(Reply::new(&()))
+            },
+            Command::RemoveItem(item) => {
+                if cart.item(&item.product_id).is_none() {
+                    return Err(ctx.fail(format!("Cannot remove item {} because it is not in the cart.", item.product_id)));
+                }
+
+                ctx.emit(Event::ItemRemoved(ItemRemoved {
+                    product_id: item.product_id,
+                }));
+                Ok(Reply::new(&()))
+            },
+            Command::GetCart(GetShoppingCart { .. }) => {
+                let items = cart.items.iter()
+                    .map(|item| LineItem {
+                        product_id: item.product_id.clone(),
+                        name: item.name.clone(),
+                        quantity: item.quantity,
+                    })
+                    .collect();
+                Ok(Reply::new(&shoppingcart::Cart { items }))
+            },
+        }
+    }
+
+    fn apply_event(&self, cart: &mut Cart, event: &Event) {
+        match event {
+            Event::ItemAdded(ItemAdded { item: Some(added) }) => {
+                match cart.items.iter_mut().find(|item| item.product_id == added.product_id) {
+                    Some(item) => item.quantity += added.quantity,
+                    None => cart.items.push(added.clone()),
+                }
+            },
+            Event::ItemAdded(ItemAdded { item: None }) => {},
+            Event::ItemRemoved(removed) => {
+                cart.items.retain(|item| item.product_id != removed.product_id);
+            },
+        }
+    }
+
+    fn snapshot(&self, cart: &Cart) -> Option<Any> {
+        let snapshot = domain::Cart { items: cart.items.clone() };
+        Some(payload::pack(&snapshot))
+    }
+
+    fn restore(&self, snapshot: &Any) -> Result<Cart, PayloadError> {
+        let snapshot: domain::Cart = payload::unpack(snapshot)?;
+        Ok(Cart { items: snapshot.items })
+    }
+}
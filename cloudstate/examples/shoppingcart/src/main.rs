@@ -0,0 +1,85 @@
+//! The shopping cart of the CloudState TCK, served to the proxy.
+//!
+//! Run it with `cargo run -p shoppingcart`, next to the proxy. The settings are
+//! read from the config directory and the environment, see `CloudState::from_config`.
+
+extern crate cloudstate;
+
+use std::env;
+use std::process;
+use log::{error, info};
+use cloudstate::serveless::CloudState;
+use tracing::Subscriber;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+fn main() {
+
+    // Cloudstate logs through log and tracing, LOG_FORMAT=json switches to JSON lines
+    // and RUST_LOG overrides the info level, e.g. RUST_LOG=cloudstate=debug.
+    // Every command is logged once handled, with its entity id, command id and name
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(FmtSpan::CLOSE);
+    let _exporter = match env::var("LOG_FORMAT") {
+        Ok(ref format) if format == "json" => init_tracing(subscriber.json().finish()),
+        _ => init_tracing(subscriber.finish()),
+    };
+    info!("Starting the shopping cart...");
+
+    let mut cloudstate = match CloudState::from_config() {
+        Ok(cloudstate) => cloudstate,
+        Err(err) => {
+            error!("{}", err);
+            process::exit(1);
+        },
+    };
+
+    let result = cloudstate
+        .descriptor(shoppingcart::DESCRIPTOR)
+        .register_entity_service(String::from(shoppingcart::SERVICE_NAME), shoppingcart::service())
+        .start();
+
+    if let Err(err) = result {
+        error!("{}", err);
+        process::exit(1);
+    }
+}
+
+// Keeps exporting the spans until dropped
+#[cfg(feature = "otlp")]
+type Exporter = Option<opentelemetry_otlp::Uninstall>;
+#[cfg(not(feature = "otlp"))]
+type Exporter = ();
+
+/// Installs the subscriber. With the otlp feature, setting OTEL_EXPORTER_OTLP_ENDPOINT
+/// (e.g. http://localhost:4317 for a local collector) also exports the spans.
+fn init_tracing<S>(subscriber: S) -> Exporter
+    where S: Subscriber + for<'span> LookupSpan<'span> + Send + Sync + 'static {
+
+    #[cfg(feature = "otlp")]
+    {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        if let Ok(endpoint) = env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+            match cloudstate::trace::otlp_layer(&endpoint) {
+                Ok((layer, exporter)) => {
+                    subscriber.with(layer).init();
+                    return Some(exporter);
+                },
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        subscriber.init();
+        None
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        subscriber.init()
+    }
+}
@@ -0,0 +1,162 @@
+//! The shopping cart example driven through the testkit.
+
+extern crate cloudstate;
+
+use prost_types::Any;
+use cloudstate::payload;
+use cloudstate::serveless::CloudState;
+use cloudstate::testkit::EventSourcedTestKit;
+use cloudstate::tls::TlsConfig;
+use shoppingcart::shoppingcart::{AddLineItem, Cart, GetShoppingCart, RemoveLineItem};
+use shoppingcart::shoppingcart::persistence::{ItemAdded, LineItem};
+
+const USER_ID: &str = "user-1";
+
+fn cloudstate() -> CloudState {
+    let mut cloudstate = CloudState::new();
+    cloudstate
+        .descriptor(shoppingcart::DESCRIPTOR)
+        .register_entity_service(shoppingcart::SERVICE_NAME.to_string(), shoppingcart::service());
+    cloudstate
+}
+
+fn add(product_id: &str, quantity: i32) -> Any {
+    payload::pack(&AddLineItem {
+        user_id: USER_ID.to_string(),
+        product_id: product_id.to_string(),
+        name: product_id.to_uppercase(),
+        quantity,
+    })
+}
+
+fn remove(product_id: &str) -> Any {
+    payload::pack(&RemoveLineItem {
+        user_id: USER_ID.to_string(),
+        product_id: product_id.to_string(),
+    })
+}
+
+fn get() -> Any {
+    payload::pack(&GetShoppingCart { user_id: USER_ID.to_string() })
+}
+
+fn added(product_id: &str, quantity: i32) -> Any {
+    payload::pack(&ItemAdded {
+        item: Some(LineItem { product_id: product_id.to_string(), name: product_id.to_uppercase(), quantity }),
+    })
+}
+
+fn items(testkit: &mut EventSourcedTestKit) -> Vec<(String, i32)> {
+    let cart: Cart = testkit.command("GetCart", get()).unwrap().reply_as().unwrap();
+    cart.items.into_iter().map(|item| (item.product_id, item.quantity)).collect()
+}
+
+#[test]
+fn add_item_emits_an_event() {
+    let cloudstate = cloudstate();
+    let mut testkit = EventSourcedTestKit::new(&cloudstate, shoppingcart::SERVICE_NAME, USER_ID).unwrap();
+
+    let reply = testkit.command("AddItem", add("apple", 2)).unwrap();
+    reply.reply_as::<()>().unwrap();
+    assert_eq!(reply.failure(), None);
+    assert_eq!(reply.events().len(), 1);
+    assert_eq!(reply.event_as::<ItemAdded>(0).unwrap().item.unwrap().quantity, 2);
+    assert!(reply.side_effects().is_empty());
+    assert_eq!(testkit.sequence(), 1);
+}
+
+#[test]
+fn add_item_merges_quantities() {
+    let cloudstate = cloudstate();
+    let mut testkit = EventSourcedTestKit::new(&cloudstate, shoppingcart::SERVICE_NAME, USER_ID).unwrap();
+
+    testkit.command("AddItem", add("apple", 2)).unwrap().reply_as::<()>().unwrap();
+    testkit.command("AddItem", add("apple", 3)).unwrap().reply_as::<()>().unwrap();
+
+    assert_eq!(items(&mut testkit), vec![("apple".to_string(), 5)]);
+}
+
+#[test]
+fn add_item_rejects_a_quantity_below_one() {
+    let cloudstate = cloudstate();
+    let mut testkit = EventSourcedTestKit::new(&cloudstate, shoppingcart::SERVICE_NAME, USER_ID).unwrap();
+
+    for quantity in &[0, -1] {
+        let reply = testkit.command("AddItem", add("apple", *quantity)).unwrap();
+        assert_eq!(reply.failure(), Some("Cannot add negative quantity of to item apple"));
+        assert!(reply.events().is_empty());
+    }
+    assert!(items(&mut testkit).is_empty());
+}
+
+#[test]
+fn remove_item_fails_when_not_in_the_cart() {
+    let cloudstate = cloudstate();
+    let mut testkit = EventSourcedTestKit::new(&cloudstate, shoppingcart::SERVICE_NAME, USER_ID).unwrap();
+
+    let reply = testkit.command("RemoveItem", remove("apple")).unwrap();
+    assert_eq!(reply.failure(), Some("Cannot remove item apple because it is not in the cart."));
+    assert_eq!(reply.reply(), None);
+    assert!(reply.events().is_empty());
+    assert_eq!(testkit.sequence(), 0);
+}
+
+#[test]
+fn replayed_events_rebuild_the_cart() {
+    let cloudstate = cloudstate();
+    let mut testkit = EventSourcedTestKit::new(&cloudstate, shoppingcart::SERVICE_NAME, USER_ID).unwrap();
+    testkit.replay(added("apple", 1)).unwrap();
+    testkit.replay(added("pear", 2)).unwrap();
+    testkit.replay(added("apple", 1)).unwrap();
+
+    assert_eq!(items(&mut testkit), vec![("apple".to_string(), 2), ("pear".to_string(), 2)]);
+    assert_eq!(testkit.sequence(), 3);
+}
+
+#[test]
+fn get_cart_keeps_the_insertion_order() {
+    let cloudstate = cloudstate();
+    let mut testkit = EventSourcedTestKit::new(&cloudstate, shoppingcart::SERVICE_NAME, USER_ID).unwrap();
+
+    for product_id in &["pear", "apple", "plum"] {
+        testkit.command("AddItem", add(product_id, 1)).unwrap();
+    }
+    testkit.command("RemoveItem", remove("apple")).unwrap().reply_as::<()>().unwrap();
+    testkit.command("AddItem", add("pear", 1)).unwrap();
+
+    assert_eq!(items(&mut testkit), vec![("pear".to_string(), 2), ("plum".to_string(), 1)]);
+}
+
+#[test]
+fn snapshot_after_five_events_restores_the_cart() {
+    let cloudstate = cloudstate();
+    let mut testkit = EventSourcedTestKit::new(&cloudstate, shoppingcart::SERVICE_NAME, USER_ID).unwrap();
+
+    for product_id in &["a", "b", "c", "d"] {
+        assert_eq!(testkit.command("AddItem", add(product_id, 1)).unwrap().snapshot(), None);
+    }
+    let reply = testkit.command("AddItem", add("a", 1)).unwrap();
+    let snapshot = reply.snapshot().cloned().expect("snapshot after 5 events");
+    assert_eq!(testkit.sequence(), 5);
+
+    let mut restored = EventSourcedTestKit::from_snapshot(&cloudstate, shoppingcart::SERVICE_NAME, USER_ID, 5, snapshot).unwrap();
+    assert_eq!(items(&mut restored), items(&mut testkit));
+    restored.replay(added("e", 1)).unwrap();
+    assert_eq!(restored.sequence(), 6);
+    assert_eq!(items(&mut restored), vec![
+        ("a".to_string(), 2), ("b".to_string(), 1), ("c".to_string(), 1), ("d".to_string(), 1), ("e".to_string(), 1),
+    ]);
+}
+
+#[test]
+fn testkit_ignores_the_address_to_listen_on() {
+    // Serving TLS on a unix domain socket fails the start, but is never served here
+    let mut cloudstate = cloudstate();
+    cloudstate
+        .unix_socket("/nonexistent/cloudstate.sock")
+        .tls(TlsConfig::new("tls/server.crt", "tls/server.key"));
+    let mut testkit = EventSourcedTestKit::new(&cloudstate, shoppingcart::SERVICE_NAME, USER_ID).unwrap();
+
+    testkit.command("AddItem", add("apple", 1)).unwrap().reply_as::<()>().unwrap();
+    assert_eq!(items(&mut testkit), vec![("apple".to_string(), 1)]);
+}
@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use heck::{CamelCase, SnakeCase};
+use prost::Message;
+use prost_types::{DescriptorProto, FileDescriptorSet};
+
+/// Writes the descriptor set of `protos`, with everything they import, to `out`. Meant for the
+/// build script of a user function, the file is what `CloudState::descriptor` expects.
+/// Runs the `protoc` found through the `PROTOC` variable, else the one on the path. Needs the
+/// `build` feature, to add to the build dependency on cloudstate.
+///
+/// ```ignore
+/// // build.rs
+/// let out = Path::new(&env::var("OUT_DIR")?).join("user-function.desc");
+/// cloudstate::build::descriptor_set(&["proto/shoppingcart.proto"], &["proto"], &out)?;
+///
+/// // main.rs
+/// cloudstate.descriptor(include_bytes!(concat!(env!("OUT_DIR"), "/user-function.desc")));
+/// ```
+pub fn descriptor_set<P: AsRef<Path>>(protos: &[P], includes: &[P], out: impl AsRef<Path>) -> io::Result<()> {
+    let protoc = env::var_os("PROTOC").unwrap_or_else(|| OsString::from("protoc"));
+    let mut command = Command::new(protoc);
+    command.arg("--include_imports").arg(flag("--descriptor_set_out=", out.as_ref()));
+    for include in includes {
+        command.arg(flag("--proto_path=", include.as_ref()));
+    }
+    for proto in protos {
+        command.arg(proto.as_ref());
+    }
+
+    let status = command.status()?;
+    if !status.success() {
+        let message = format!("protoc failed to build the descriptor set: {}", status);
+        return Err(io::Error::new(io::ErrorKind::Other, message));
+    }
+    Ok(())
+}
+
+fn flag(name: &str, path: &Path) -> OsString {
+    let mut flag = OsString::from(name);
+    flag.push(path);
+    flag
+}
+
+/// Writes `{package}.names.rs` to `out_dir` for every package of the descriptor set at
+/// `descriptor_set`, implementing `MessageName` for the messages that prost generates in
+/// `{package}.rs`. Include it next to them with `include_message_names!`.
+///
+/// ```ignore
+/// // build.rs, after descriptor_set
+/// cloudstate::build::message_names(&out, env::var("OUT_DIR")?)?;
+///
+/// // main.rs
+/// pub mod shoppingcart {
+///     include!(concat!(env!("OUT_DIR"), "/com.example.shoppingcart.rs"));
+///     cloudstate::include_message_names!("com.example.shoppingcart");
+/// }
+/// ```
+pub fn message_names(descriptor_set: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> io::Result<()> {
+    let bytes = fs::read(descriptor_set)?;
+    let descriptors = FileDescriptorSet::decode(&bytes[..])
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    // Files of the same package are generated into the same module
+    let mut packages: BTreeMap<String, String> = BTreeMap::new();
+    for file in descriptors.file {
+        let package = file.package.unwrap_or_default();
+        let code = packages.entry(package.clone()).or_default();
+        for message in &file.message_type {
+            impl_message_name(&package, "", message, code);
+        }
+    }
+
+    for (package, code) in packages {
+        if !code.is_empty() {
+            fs::write(out_dir.as_ref().join(format!("{}.names.rs", package)), code)?;
+        }
+    }
+    Ok(())
+}
+
+// Named the way prost names the structs, nested messages live in a module named after their parent
+fn impl_message_name(scope: &str, module: &str, message: &DescriptorProto, code: &mut String) {
+    let name = match message.name.as_ref() {
+        Some(name) => name,
+        None => return,
+    };
+    // Map entries are generated as HashMaps
+    if message.options.as_ref().and_then(|options| options.map_entry).unwrap_or(false) {
+        return;
+    }
+
+    let full_name = if scope.is_empty() { name.clone() } else { format!("{}.{}", scope, name) };
+    code.push_str(&format!(
+        "impl ::cloudstate::payload::MessageName for {}{} {{\n    const FULL_NAME: &'static str = {:?};\n}}\n",
+        module, rust_type(name), full_name,
+    ));
+
+    let module = format!("{}{}::", module, rust_module(name));
+    for nested in &message.nested_type {
+        impl_message_name(&full_name, &module, nested, code);
+    }
+}
+
+fn rust_type(name: &str) -> String {
+    match name.to_camel_case() {
+        ref ident if ident == "Self" => String::from("Self_"),
+        ident => ident,
+    }
+}
+
+fn rust_module(name: &str) -> String {
+    let ident = name.to_snake_case();
+    match ident.as_str() {
+        "self" | "super" | "extern" | "crate" => ident + "_",
+        "as" | "break" | "const" | "continue" | "else" | "enum" | "false" | "fn" | "for" | "if" | "impl" | "in"
+        | "let" | "loop" | "match" | "mod" | "move" | "mut" | "pub" | "ref" | "return" | "static" | "struct"
+        | "trait" | "true" | "type" | "unsafe" | "use" | "where" | "while" | "dyn" | "abstract" | "become"
+        | "box" | "do" | "final" | "macro" | "override" | "priv" | "typeof" | "unsized" | "virtual" | "yield"
+        | "async" | "await" | "try" => format!("r#{}", ident),
+        _ => ident,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use prost::Message;
+    use prost_types::{DescriptorProto, FileDescriptorProto, FileDescriptorSet, MessageOptions};
+
+    fn message(name: &str, nested_type: Vec<DescriptorProto>) -> DescriptorProto {
+        DescriptorProto {
+            name: Some(name.to_string()),
+            nested_type,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn message_names_of_nested_messages() {
+        let entry = DescriptorProto {
+            options: Some(MessageOptions { map_entry: Some(true), ..Default::default() }),
+            ..message("QuantitiesEntry", Vec::new())
+        };
+        let file = FileDescriptorProto {
+            package: Some("com.example".to_string()),
+            message_type: vec![message("Cart", vec![message("line_item", Vec::new()), entry])],
+            ..Default::default()
+        };
+        let mut bytes = Vec::new();
+        FileDescriptorSet { file: vec![file] }.encode(&mut bytes).unwrap();
+
+        let dir = env::temp_dir().join(format!("cloudstate-names-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("test.desc"), bytes).unwrap();
+        super::message_names(dir.join("test.desc"), &dir).unwrap();
+        let code = fs::read_to_string(dir.join("com.example.names.rs")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(code.contains("MessageName for Cart {\n    const FULL_NAME: &'static str = \"com.example.Cart\";"), "{}", code);
+        assert!(code.contains("MessageName for cart::LineItem {\n    const FULL_NAME: &'static str = \"com.example.Cart.line_item\";"), "{}", code);
+        assert!(!code.contains("QuantitiesEntry"), "{}", code);
+    }
+}
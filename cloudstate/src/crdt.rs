@@ -0,0 +1,374 @@
+use std::time::Instant;
+use log::{debug, warn};
+use tracing::{field, info_span};
+use crate::handlers::handler::CrdtHandler;
+use crate::protocol::Options;
+use crate::trace::TraceContext;
+use crate::serveless::CRDT;
+use crate::protocol::spec::Failure;
+use crate::protocol::spec::crdt::{
+    crdt_stream_in, crdt_stream_out,
+    CrdtInit, CrdtStreamIn, CrdtStreamOut,
+};
+
+/// State of a single `Crdt/handle` stream.
+///
+/// The proxy first sends an init message with the current state, if any. Afterwards
+/// full states and deltas from other replicas are interleaved with commands and
+/// cancellations of streamed commands.
+pub struct CrdtStream {
+    opts: Options,
+    service_name: String,
+    entity_id: String,
+    /// Trace context of the stream, the parent of the command spans.
+    trace: Option<TraceContext>,
+    handler: Option<Box<dyn CrdtHandler>>,
+}
+
+impl CrdtStream {
+
+    pub fn new(opts: Options) -> Self {
+        CrdtStream {
+            opts,
+            service_name: String::from(""),
+            entity_id: String::from(""),
+            trace: None,
+            handler: None,
+        }
+    }
+
+    /// Sets the trace context of the stream, see `TraceContext::from_headers`.
+    pub fn with_trace(mut self, trace: Option<TraceContext>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Processes one incoming message and returns the messages to send back.
+    /// An error is a failure that must be sent to the proxy before closing the stream.
+    pub fn handle_message(&mut self, msg: CrdtStreamIn) -> Result<Vec<CrdtStreamOut>, Failure> {
+        match msg.message {
+            Some(crdt_stream_in::Message::Init(init)) => {
+                self.handle_init(init)?;
+                Ok(self.changed())
+            },
+            Some(crdt_stream_in::Message::State(state)) => {
+                debug!("Received state for entity {:?}", self.entity_id);
+                self.handler_mut(0)?
+                    .handle_state(state)
+                    .map_err(|err| Failure::new(0, err))?;
+                Ok(self.changed())
+            },
+            Some(crdt_stream_in::Message::Changed(delta)) => {
+                debug!("Received delta for entity {:?}", self.entity_id);
+                self.opts.metrics.crdt_delta_received(&self.service_name);
+                self.handler_mut(0)?
+                    .handle_delta(delta)
+                    .map_err(|err| Failure::new(0, err))?;
+                Ok(self.changed())
+            },
+            Some(crdt_stream_in::Message::Deleted(_)) => {
+                debug!("Entity {:?} deleted", self.entity_id);
+                self.handler_mut(0)?
+                    .handle_delete()
+                    .map_err(|err| Failure::new(0, err))?;
+                Ok(Vec::new())
+            },
+            Some(crdt_stream_in::Message::Command(command)) => {
+                let span = info_span!("command",
+                    entity_id = %command.entity_id, command_id = command.id, command_name = %command.name,
+                    trace_id = field::Empty, span_id = field::Empty, parent_id = field::Empty);
+                if let Some(parent) = self.trace {
+                    parent.enter(&span);
+                }
+                let _enter = span.enter();
+                debug!("Received command {:?} ({:?}) for entity {:?}", command.name, command.id, command.entity_id);
+                let command_id = command.id;
+                let command_name = command.name.clone();
+                let started = Instant::now();
+                let result = self.handler_mut(command_id)?
+                    .handle_command(command)
+                    .map_err(|err| Failure::new(command_id, err));
+                self.opts.metrics.command_handled(&self.service_name, &command_name, started.elapsed(), result.is_err());
+                let mut reply = result?;
+                reply.command_id = command_id;
+
+                let changed = reply.state_action.is_some();
+                if changed {
+                    self.opts.metrics.crdt_delta_sent(&self.service_name);
+                }
+                let mut out = vec![CrdtStreamOut {
+                    message: Some(crdt_stream_out::Message::Reply(reply)),
+                }];
+                if changed {
+                    out.extend(self.changed());
+                }
+                Ok(out)
+            },
+            Some(crdt_stream_in::Message::StreamCancelled(cancelled)) => {
+                debug!("Streamed command {:?} cancelled for entity {:?}", cancelled.id, cancelled.entity_id);
+                let command_id = cancelled.id;
+                let mut response = self.handler_mut(command_id)?
+                    .handle_stream_cancelled(cancelled)
+                    .map_err(|err| Failure::new(command_id, err))?;
+                response.command_id = command_id;
+
+                let changed = response.state_action.is_some();
+                if changed {
+                    self.opts.metrics.crdt_delta_sent(&self.service_name);
+                }
+                let mut out = vec![CrdtStreamOut {
+                    message: Some(crdt_stream_out::Message::StreamCancelledResponse(response)),
+                }];
+                if changed {
+                    out.extend(self.changed());
+                }
+                Ok(out)
+            },
+            None => Err(Failure::new(0, "Received an empty message")),
+        }
+    }
+
+    fn handle_init(&mut self, init: CrdtInit) -> Result<(), Failure> {
+        if self.handler.is_some() {
+            return Err(Failure::new(0, format!("Entity {:?} already initialized", self.entity_id)));
+        }
+
+        let service = self.opts.entity_service(&init.service_name, CRDT)
+            .map_err(|err| Failure::new(0, err))?;
+        let factory = service.crdt_handler.as_ref()
+            .ok_or_else(|| Failure::new(0, format!("No CRDT handler registered for service {:?}", init.service_name)))?;
+
+        debug!("Initializing CRDT entity {:?} of service {:?}", init.entity_id, init.service_name);
+        let mut handler = factory.create(&init.entity_id);
+        if let Some(state) = init.state {
+            handler.handle_state(state).map_err(|err| Failure::new(0, err))?;
+        }
+
+        self.service_name = init.service_name;
+        self.entity_id = init.entity_id;
+        self.handler = Some(handler);
+        Ok(())
+    }
+
+    fn changed(&mut self) -> Vec<CrdtStreamOut> {
+        match self.handler.as_mut() {
+            Some(handler) => handler.changed()
+                .into_iter()
+                .map(|msg| CrdtStreamOut {
+                    message: Some(crdt_stream_out::Message::StreamedMessage(msg)),
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn handler_mut(&mut self, command_id: i64) -> Result<&mut Box<dyn CrdtHandler>, Failure> {
+        match self.handler.as_mut() {
+            Some(handler) => Ok(handler),
+            None => {
+                warn!("Received a message before the entity was initialized");
+                Err(Failure::new(command_id, "Entity not initialized"))
+            },
+        }
+    }
+}
+
+pub fn failure_message(failure: Failure) -> CrdtStreamOut {
+    CrdtStreamOut {
+        message: Some(crdt_stream_out::Message::Failure(failure)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+    use std::sync::{Arc, Mutex};
+    use crate::handlers::handler::CrdtHandler;
+    use crate::protocol::spec::{Command, Failure, StreamCancelled};
+    use crate::protocol::spec::crdt::{
+        crdt_state, crdt_state_action, crdt_stream_in, crdt_stream_out,
+        CrdtDelete, CrdtDelta, CrdtInit, CrdtReply, CrdtState, CrdtStateAction,
+        CrdtStreamCancelledResponse, CrdtStreamedMessage, CrdtStreamIn, CrdtStreamOut, GCounterState,
+    };
+    use crate::fixture::{self, SERVICE_NAME};
+    use crate::serveless::EntityService;
+    use super::CrdtStream;
+
+    /// Records the messages it handles. Unless streamed, commands update the state,
+    /// and the streamed commands get a message on every change.
+    struct Recorder {
+        log: Arc<Mutex<Vec<String>>>,
+        streamed: Vec<i64>,
+        changed: bool,
+    }
+
+    impl CrdtHandler for Recorder {
+
+        fn handle_state(&mut self, _state: CrdtState) -> Result<(), String> {
+            self.log.lock().unwrap().push(String::from("state"));
+            self.changed = true;
+            Ok(())
+        }
+
+        fn handle_delta(&mut self, _delta: CrdtDelta) -> Result<(), String> {
+            self.log.lock().unwrap().push(String::from("delta"));
+            self.changed = true;
+            Ok(())
+        }
+
+        fn handle_delete(&mut self) -> Result<(), String> {
+            self.log.lock().unwrap().push(String::from("delete"));
+            Ok(())
+        }
+
+        fn handle_command(&mut self, command: Command) -> Result<CrdtReply, String> {
+            self.log.lock().unwrap().push(format!("command {}", command.id));
+            if command.streamed {
+                self.streamed.push(command.id);
+                return Ok(CrdtReply { streamed: true, ..Default::default() });
+            }
+
+            self.changed = true;
+            let update = CrdtStateAction {
+                action: Some(crdt_state_action::Action::Update(CrdtDelta::default())),
+                ..Default::default()
+            };
+            Ok(CrdtReply { state_action: Some(update), ..Default::default() })
+        }
+
+        fn handle_stream_cancelled(&mut self, cancelled: StreamCancelled) -> Result<CrdtStreamCancelledResponse, String> {
+            self.log.lock().unwrap().push(format!("cancelled {}", cancelled.id));
+            self.streamed.retain(|id| *id != cancelled.id);
+            Ok(CrdtStreamCancelledResponse::default())
+        }
+
+        fn changed(&mut self) -> Vec<CrdtStreamedMessage> {
+            if !mem::replace(&mut self.changed, false) {
+                return Vec::new();
+            }
+            self.streamed.iter()
+                .map(|id| CrdtStreamedMessage { command_id: *id, ..Default::default() })
+                .collect()
+        }
+    }
+
+    fn stream(log: &Arc<Mutex<Vec<String>>>) -> CrdtStream {
+        let log = log.clone();
+        let service = EntityService::new()
+            .crdt_handler(move |_| Box::new(Recorder { log: log.clone(), streamed: Vec::new(), changed: false }) as Box<dyn CrdtHandler>)
+            .crdt();
+
+        CrdtStream::new(fixture::cloudstate(service).entity_options().unwrap())
+    }
+
+    fn send(stream: &mut CrdtStream, message: crdt_stream_in::Message) -> Result<Vec<CrdtStreamOut>, Failure> {
+        stream.handle_message(CrdtStreamIn { message: Some(message) })
+    }
+
+    fn init(state: Option<CrdtState>) -> crdt_stream_in::Message {
+        crdt_stream_in::Message::Init(CrdtInit {
+            service_name: SERVICE_NAME.to_string(),
+            entity_id: "counter-1".to_string(),
+            state,
+        })
+    }
+
+    fn gcounter(value: u64) -> CrdtState {
+        CrdtState { state: Some(crdt_state::State::Gcounter(GCounterState { value })) }
+    }
+
+    fn command(id: i64, streamed: bool) -> crdt_stream_in::Message {
+        crdt_stream_in::Message::Command(Command {
+            entity_id: "counter-1".to_string(),
+            id,
+            name: "Increment".to_string(),
+            payload: None,
+            streamed,
+        })
+    }
+
+    fn cancelled(id: i64) -> crdt_stream_in::Message {
+        crdt_stream_in::Message::StreamCancelled(StreamCancelled { entity_id: "counter-1".to_string(), id })
+    }
+
+    // Command ids of the replies, the streamed messages and the cancelled responses, in order
+    fn command_ids(out: Vec<CrdtStreamOut>) -> Vec<(&'static str, i64)> {
+        out.into_iter()
+            .map(|out| match out.message {
+                Some(crdt_stream_out::Message::Reply(reply)) => ("reply", reply.command_id),
+                Some(crdt_stream_out::Message::StreamedMessage(message)) => ("streamed", message.command_id),
+                Some(crdt_stream_out::Message::StreamCancelledResponse(response)) => ("cancelled", response.command_id),
+                message => panic!("Unexpected message {:?}", message),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn messages_before_init_fail() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut stream = stream(&log);
+
+        let messages = vec![
+            (command(3, false), 3),
+            (crdt_stream_in::Message::State(gcounter(1)), 0),
+            (crdt_stream_in::Message::Changed(CrdtDelta::default()), 0),
+            (crdt_stream_in::Message::Deleted(CrdtDelete::default()), 0),
+            (cancelled(4), 4),
+        ];
+        for (message, command_id) in messages {
+            let failure = send(&mut stream, message).unwrap_err();
+            assert_eq!(failure, Failure::new(command_id, "Entity not initialized"));
+        }
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn init_twice_fails() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut stream = stream(&log);
+        send(&mut stream, init(None)).unwrap();
+
+        let failure = send(&mut stream, init(None)).unwrap_err();
+        assert_eq!(failure.description, "Entity \"counter-1\" already initialized");
+    }
+
+    #[test]
+    fn state_delta_and_delete_in_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut stream = stream(&log);
+
+        send(&mut stream, init(Some(gcounter(1)))).unwrap();
+        send(&mut stream, crdt_stream_in::Message::Changed(CrdtDelta::default())).unwrap();
+        send(&mut stream, crdt_stream_in::Message::State(gcounter(3))).unwrap();
+        assert!(send(&mut stream, crdt_stream_in::Message::Deleted(CrdtDelete::default())).unwrap().is_empty());
+        send(&mut stream, crdt_stream_in::Message::Changed(CrdtDelta::default())).unwrap();
+
+        assert_eq!(*log.lock().unwrap(), vec!["state", "delta", "state", "delete", "delta"]);
+    }
+
+    #[test]
+    fn changes_are_streamed_after_the_reply() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut stream = stream(&log);
+        send(&mut stream, init(None)).unwrap();
+
+        assert_eq!(command_ids(send(&mut stream, command(1, true)).unwrap()), vec![("reply", 1)]);
+        assert_eq!(command_ids(send(&mut stream, command(2, false)).unwrap()), vec![("reply", 2), ("streamed", 1)]);
+        assert_eq!(
+            command_ids(send(&mut stream, crdt_stream_in::Message::Changed(CrdtDelta::default())).unwrap()),
+            vec![("streamed", 1)],
+        );
+    }
+
+    #[test]
+    fn stream_cancelled_ends_the_streamed_messages() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut stream = stream(&log);
+        send(&mut stream, init(None)).unwrap();
+        send(&mut stream, command(1, true)).unwrap();
+
+        assert_eq!(command_ids(send(&mut stream, cancelled(1)).unwrap()), vec![("cancelled", 1)]);
+        assert!(send(&mut stream, crdt_stream_in::Message::Changed(CrdtDelta::default())).unwrap().is_empty());
+        assert_eq!(*log.lock().unwrap(), vec!["command 1", "cancelled 1", "delta"]);
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use prost::Message;
+use prost_types::FileDescriptorSet;
+
+/// An RPC method of an entity service, with the fully qualified names of its message types.
+#[derive(Debug, Clone)]
+pub struct Method {
+    pub name: String,
+    pub input_type: String,
+    pub output_type: String,
+}
+
+/// Finds the methods of the fully qualified service `service_name` in a serialized `FileDescriptorSet`.
+pub fn service_methods(descriptor_set: &[u8], service_name: &str) -> Result<HashMap<String, Method>, String> {
+    let descriptors = FileDescriptorSet::decode(descriptor_set)
+        .map_err(|err| format!("Unable to decode the descriptor set: {}", err))?;
+
+    for file in descriptors.file {
+        let package = file.package.unwrap_or_default();
+        for service in file.service {
+            let name = service.name.unwrap_or_default();
+            let full_name = if package.is_empty() { name } else { format!("{}.{}", package, name) };
+            if full_name != service_name {
+                continue;
+            }
+
+            return Ok(service.method.into_iter()
+                .map(|method| {
+                    let method = Method {
+                        name: method.name.unwrap_or_default(),
+                        input_type: type_name(method.input_type),
+                        output_type: type_name(method.output_type),
+                    };
+                    (method.name.clone(), method)
+                })
+                .collect());
+        }
+    }
+
+    Err(format!("Service {:?} is not declared in the descriptor set", service_name))
+}
+
+// Descriptors reference types by their fully qualified name with a leading dot
+fn type_name(name: Option<String>) -> String {
+    let name = name.unwrap_or_default();
+    name.trim_start_matches('.').to_string()
+}
@@ -0,0 +1,79 @@
+use std::fmt;
+use std::io;
+use tonic::{Code, Status};
+
+/// Errors of the CloudState support library.
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid settings, from the builders, a configuration file or the environment.
+    Config(String),
+    /// Missing or invalid descriptor set, or entity services not matching it.
+    Descriptor(String),
+    /// The server could not be started or failed while serving.
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    /// The proxy is incompatible or did not follow the CloudState protocol.
+    Protocol(String),
+    /// A handler registered by the user function failed.
+    UserHandler(String),
+}
+
+impl fmt::Display for Error {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(message) => write!(f, "Invalid configuration: {}", message),
+            Error::Descriptor(message) => write!(f, "Invalid descriptor: {}", message),
+            Error::Transport(err) => write!(f, "Transport error: {}", err),
+            Error::Protocol(message) => write!(f, "Protocol error: {}", message),
+            Error::UserHandler(message) => write!(f, "Handler error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+
+    fn from(err: io::Error) -> Self {
+        Error::Transport(Box::new(err))
+    }
+}
+
+impl From<tonic::transport::Error> for Error {
+
+    fn from(err: tonic::transport::Error) -> Self {
+        Error::Transport(Box::new(err))
+    }
+}
+
+impl From<hyper::Error> for Error {
+
+    fn from(err: hyper::Error) -> Self {
+        Error::Transport(Box::new(err))
+    }
+}
+
+/// Status sent to the proxy for a failed call.
+impl From<Error> for Status {
+
+    fn from(err: Error) -> Self {
+        let code = match &err {
+            Error::Config(_) | Error::Descriptor(_) | Error::Protocol(_) => Code::FailedPrecondition,
+            Error::Transport(_) => Code::Unavailable,
+            Error::UserHandler(_) => Code::Unknown,
+        };
+        let message = match err {
+            Error::UserHandler(message) => message,
+            err => err.to_string(),
+        };
+        Status::new(code, message)
+    }
+}
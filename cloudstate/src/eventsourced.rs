@@ -0,0 +1,755 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use log::{debug, warn};
+use tracing::{debug_span, field, info_span};
+use prost_types::Any;
+use crate::handlers::handler::{CommandContext, EventSourcedEntity, EventSourcedHandler, Payload};
+use crate::descriptor::Method;
+use crate::payload::{self, CommandRegistry, TypeRegistry};
+use crate::protocol::Options;
+use crate::trace::TraceContext;
+use crate::serveless::EVENT_SOURCED;
+use crate::protocol::spec::{client_action, ClientAction, Command, Failure, Reply};
+use crate::protocol::spec::eventsourced::{
+    event_sourced_stream_in, event_sourced_stream_out,
+    EventSourcedEvent, EventSourcedInit, EventSourcedReply, EventSourcedSnapshot,
+    EventSourcedStreamIn, EventSourcedStreamOut,
+};
+
+/// State of a single `EventSourced/handle` stream.
+///
+/// The proxy first sends an init message, then replays the persisted events and
+/// finally sends the commands, each of which must be answered with exactly one reply.
+pub struct EntityStream {
+    opts: Options,
+    service_name: String,
+    entity_id: String,
+    /// Trace context of the stream, the parent of the command spans.
+    trace: Option<TraceContext>,
+    handler: Option<Box<dyn EventSourcedHandler>>,
+    methods: HashMap<String, Method>,
+    sequence: i64,
+    snapshot_every: u16,
+    events_since_snapshot: usize,
+    /// Set on init until the first command, to measure the replay.
+    replay_started: Option<Instant>,
+}
+
+impl EntityStream {
+
+    pub fn new(opts: Options) -> Self {
+        EntityStream {
+            opts,
+            service_name: String::from(""),
+            entity_id: String::from(""),
+            trace: None,
+            handler: None,
+            methods: HashMap::new(),
+            sequence: 0,
+            snapshot_every: 0,
+            events_since_snapshot: 0,
+            replay_started: None,
+        }
+    }
+
+    /// Sets the trace context of the stream, see `TraceContext::from_headers`.
+    pub fn with_trace(mut self, trace: Option<TraceContext>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Processes one incoming message and returns the message to send back, if any.
+    /// An error is a failure that must be sent to the proxy before closing the stream.
+    pub fn handle_message(&mut self, msg: EventSourcedStreamIn) -> Result<Option<EventSourcedStreamOut>, Failure> {
+        match msg.message {
+            Some(event_sourced_stream_in::Message::Init(init)) => {
+                self.handle_init(init)?;
+                Ok(None)
+            },
+            Some(event_sourced_stream_in::Message::Event(event)) => {
+                if event.sequence <= self.sequence {
+                    debug!("Skipping event {:?} already contained in the snapshot of entity {:?}", event.sequence, self.entity_id);
+                    return Ok(None);
+                }
+
+                let span = debug_span!("event", entity_id = %self.entity_id, sequence = event.sequence);
+                let _enter = span.enter();
+                debug!("Replaying event {:?} for entity {:?}", event.sequence, self.entity_id);
+                let sequence = event.sequence;
+                self.handler_mut(0)?
+                    .handle_event(event)
+                    .map_err(|err| Failure::new(0, err))?;
+                self.sequence = sequence;
+                self.events_since_snapshot += 1;
+                Ok(None)
+            },
+            Some(event_sourced_stream_in::Message::Command(command)) => {
+                // At info level, so that the spans exported with the default filter have a parent
+                let span = info_span!("command",
+                    entity_id = %command.entity_id, command_id = command.id, command_name = %command.name,
+                    trace_id = field::Empty, span_id = field::Empty, parent_id = field::Empty);
+                if let Some(parent) = self.trace {
+                    parent.enter(&span);
+                }
+                let _enter = span.enter();
+                debug!("Received command {:?} ({:?}) for entity {:?}", command.name, command.id, command.entity_id);
+                if let Some(replay_started) = self.replay_started.take() {
+                    self.opts.metrics.replay_finished(&self.service_name, replay_started.elapsed());
+                }
+
+                let command_name = command.name.clone();
+                let started = Instant::now();
+                let result = self.handle_command(command);
+                let failed = match &result {
+                    Ok(reply) => is_failure(reply),
+                    Err(_) => true,
+                };
+                self.opts.metrics.command_handled(&self.service_name, &command_name, started.elapsed(), failed);
+
+                Ok(Some(EventSourcedStreamOut {
+                    message: Some(event_sourced_stream_out::Message::Reply(result?)),
+                }))
+            },
+            None => Err(Failure::new(0, "Received an empty message")),
+        }
+    }
+
+    fn handle_command(&mut self, command: Command) -> Result<EventSourcedReply, Failure> {
+        let command_id = command.id;
+        // The proxy fails the call, the entity stream stays open
+        let method = match self.method(&command) {
+            Ok(method) => method,
+            Err(failure) => return Ok(failure_reply(failure)),
+        };
+        let mut reply = self.handler_mut(command_id)?
+            .handle_command(command, &method)
+            .map_err(|err| Failure::new(command_id, err))?;
+        reply.command_id = command_id;
+        // The handler applied the events already, only a restart rebuilds its state without them
+        if let Some(client_action::Action::Reply(reply)) = reply.client_action.as_ref().and_then(|action| action.action.as_ref()) {
+            check_reply(reply, &method, command_id)?;
+        }
+
+        if !reply.events.is_empty() {
+            self.sequence += reply.events.len() as i64;
+            self.events_since_snapshot += reply.events.len();
+            self.opts.metrics.events_emitted(&self.service_name, reply.events.len());
+
+            if self.snapshot_every > 0 && self.events_since_snapshot >= usize::from(self.snapshot_every) {
+                reply.snapshot = self.handler_mut(command_id)?.snapshot();
+                if reply.snapshot.is_some() {
+                    debug!("Snapshotting entity {:?} at sequence {:?}", self.entity_id, self.sequence);
+                    self.events_since_snapshot = 0;
+                    self.opts.metrics.snapshot_written(&self.service_name);
+                }
+            }
+        }
+
+        Ok(reply)
+    }
+
+    fn handle_init(&mut self, init: EventSourcedInit) -> Result<(), Failure> {
+        if self.handler.is_some() {
+            return Err(Failure::new(0, format!("Entity {:?} already initialized", self.entity_id)));
+        }
+
+        let service = self.opts.entity_service(&init.service_name, EVENT_SOURCED)
+            .map_err(|err| Failure::new(0, err))?;
+        let factory = service.handler.as_ref()
+            .ok_or_else(|| Failure::new(0, format!("No handler registered for service {:?}", init.service_name)))?;
+
+        debug!("Initializing entity {:?} of service {:?}", init.entity_id, init.service_name);
+        self.replay_started = Some(Instant::now());
+        let mut handler = factory.create(&init.entity_id);
+        self.snapshot_every = service.snapshot_every;
+        self.methods = service.methods.clone();
+        if let Some(snapshot) = init.snapshot {
+            debug!("Restoring entity {:?} from snapshot at sequence {:?}", init.entity_id, snapshot.snapshot_sequence);
+            self.sequence = snapshot.snapshot_sequence;
+            handler.handle_snapshot(snapshot).map_err(|err| Failure::new(0, err))?;
+        }
+
+        self.service_name = init.service_name;
+        self.entity_id = init.entity_id;
+        self.handler = Some(handler);
+        Ok(())
+    }
+
+    /// Finds the method called by a command and checks the payload is of its input type.
+    fn method(&self, command: &Command) -> Result<Method, Failure> {
+        let method = self.methods.get(&command.name)
+            .ok_or_else(|| Failure::new(command.id, format!("Unknown command {:?}", command.name)))?;
+
+        let input_type = payload::type_url(&method.input_type);
+        match command.payload.as_ref() {
+            Some(payload) if payload.type_url != input_type => Err(Failure::new(
+                command.id,
+                format!("Command {:?} expects {:?} but got {:?}", command.name, input_type, payload.type_url),
+            )),
+            _ => Ok(method.clone()),
+        }
+    }
+
+    fn handler_mut(&mut self, command_id: i64) -> Result<&mut Box<dyn EventSourcedHandler>, Failure> {
+        match self.handler.as_mut() {
+            Some(handler) => Ok(handler),
+            None => {
+                warn!("Received a message before the entity was initialized");
+                Err(Failure::new(command_id, "Entity not initialized"))
+            },
+        }
+    }
+}
+
+/// Checks that a reply is of the output type of the method.
+fn check_reply(reply: &Reply, method: &Method, command_id: i64) -> Result<(), Failure> {
+    let output_type = payload::type_url(&method.output_type);
+    match reply.payload.as_ref() {
+        Some(payload) if payload.type_url != output_type => Err(Failure::new(
+            command_id,
+            format!("Command {:?} returns {:?} but the reply is a {:?}", method.name, output_type, payload.type_url),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Reply failing a command without changing the entity.
+fn failure_reply(failure: Failure) -> EventSourcedReply {
+    EventSourcedReply {
+        command_id: failure.command_id,
+        client_action: Some(ClientAction { action: Some(client_action::Action::Failure(failure)) }),
+        side_effects: Vec::new(),
+        events: Vec::new(),
+        snapshot: None,
+    }
+}
+
+// A failure returned by the entity is answered like any other reply, but counts as failed
+fn is_failure(reply: &EventSourcedReply) -> bool {
+    match reply.client_action.as_ref().and_then(|action| action.action.as_ref()) {
+        Some(client_action::Action::Failure(_)) => true,
+        _ => false,
+    }
+}
+
+pub fn failure_message(failure: Failure) -> EventSourcedStreamOut {
+    EventSourcedStreamOut {
+        message: Some(event_sourced_stream_out::Message::Failure(failure)),
+    }
+}
+
+/// Payload types registered by an `EventSourcedEntity`, shared by all its instances.
+pub struct EntityTypes<E: EventSourcedEntity> {
+    commands: CommandRegistry<E::Command>,
+    events: TypeRegistry<E::Event>,
+}
+
+impl<E: EventSourcedEntity> EntityTypes<E> {
+
+    pub fn new(entity: &E) -> Self {
+        let mut commands = CommandRegistry::new();
+        let mut events = TypeRegistry::new();
+        entity.register_types(&mut commands, &mut events);
+
+        EntityTypes {
+            commands,
+            events,
+        }
+    }
+
+    pub fn type_urls(&self) -> impl Iterator<Item = &String> {
+        self.commands.methods().map(|(_, type_url)| type_url).chain(self.events.type_urls())
+    }
+
+    /// Names of the methods with the type url of their command.
+    pub fn commands(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.commands.methods()
+    }
+
+    /// Methods and event types registered more than once.
+    pub fn duplicates(&self) -> impl Iterator<Item = &String> {
+        self.commands.duplicates().iter().chain(self.events.duplicates())
+    }
+}
+
+/// Runs an `EventSourcedEntity` as the handler of one entity instance, keeping its state.
+pub struct EntityInstance<E: EventSourcedEntity> {
+    entity: Arc<E>,
+    types: Arc<EntityTypes<E>>,
+    state: E::State,
+}
+
+impl<E: EventSourcedEntity> EntityInstance<E> {
+
+    pub fn new(entity: Arc<E>, types: Arc<EntityTypes<E>>) -> Self {
+        EntityInstance {
+            entity,
+            types,
+            state: Default::default(),
+        }
+    }
+}
+
+impl<E: EventSourcedEntity> EventSourcedHandler for EntityInstance<E> {
+
+    fn snapshot(&self) -> Option<Any> {
+        self.entity.snapshot(&self.state)
+    }
+
+    fn handle_snapshot(&mut self, snapshot: EventSourcedSnapshot) -> Result<(), String> {
+        let payload = snapshot.snapshot
+            .ok_or_else(|| String::from("Received a snapshot without payload"))?;
+        self.state = self.entity.restore(&payload)
+            .map_err(|err| format!("Invalid snapshot: {}", err))?;
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: EventSourcedEvent) -> Result<(), String> {
+        let sequence = event.sequence;
+        let payload = event.payload
+            .ok_or_else(|| format!("Received event {:?} without payload", sequence))?;
+        let event = self.types.events.unpack(&payload)
+            .map_err(|err| format!("Invalid event {:?}: {}", sequence, err))?;
+        self.entity.apply_event(&mut self.state, &event);
+        Ok(())
+    }
+
+    fn handle_command(&mut self, mut command: Command, method: &Method) -> Result<EventSourcedReply, String> {
+        let payload = match command.payload.take() {
+            Some(payload) => payload,
+            None => return Ok(failure_reply(Failure::new(command.id, format!("Received command {:?} without payload", command.name)))),
+        };
+        let cmd = match self.types.commands.unpack(&method.name, &payload) {
+            Ok(cmd) => cmd,
+            Err(err) => return Ok(failure_reply(Failure::new(command.id, format!("Invalid command {:?}: {}", command.name, err)))),
+        };
+
+        let mut ctx = CommandContext::new(&command);
+        let result = self.entity.handle_command(&self.state, cmd, &mut ctx);
+        let (emitted, forward, effects) = ctx.into_parts();
+
+        // A forward replaces the reply, whatever its type
+        let result = match (result, &forward) {
+            (Ok(reply), None) => check_reply(&reply, method, command.id).map(|_| reply),
+            (result, _) => result,
+        };
+
+        let (action, events, side_effects) = match result {
+            Ok(reply) => {
+                let mut events = Vec::new();
+                for event in emitted {
+                    self.entity.apply_event(&mut self.state, &event);
+                    events.push(event.to_any());
+                }
+
+                let action = match forward {
+                    Some(forward) => client_action::Action::Forward(forward),
+                    None => client_action::Action::Reply(reply),
+                };
+                (action, events, effects)
+            },
+            Err(mut failure) => {
+                failure.command_id = command.id;
+                (client_action::Action::Failure(failure), Vec::new(), Vec::new())
+            },
+        };
+
+        Ok(EventSourcedReply {
+            command_id: command.id,
+            client_action: Some(ClientAction { action: Some(action) }),
+            side_effects,
+            events,
+            snapshot: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::fmt;
+    use prost_types::Any;
+    use tracing::{Event, Level, Metadata, Subscriber};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use crate::descriptor::Method;
+    use crate::handlers::handler::{CommandContext, EventSourcedEntity, EventSourcedHandler, Payload};
+    use crate::payload::{self, CommandRegistry, TypeRegistry};
+    use crate::protocol::spec::{client_action, ClientAction, Command, Failure, Reply};
+    use crate::protocol::spec::eventsourced::{
+        event_sourced_stream_in, event_sourced_stream_out,
+        EventSourcedEvent, EventSourcedInit, EventSourcedReply, EventSourcedSnapshot, EventSourcedStreamIn,
+    };
+    use crate::fixture::{self, SERVICE_NAME};
+    use crate::serveless::EntityService;
+    use crate::trace::TraceContext;
+    use super::EntityStream;
+
+    /// Counts the events, emitting one per command, and records the replayed sequences.
+    struct Counter {
+        count: u64,
+        replayed: Arc<Mutex<Vec<i64>>>,
+    }
+
+    impl EventSourcedHandler for Counter {
+
+        fn handle_snapshot(&mut self, snapshot: EventSourcedSnapshot) -> Result<(), String> {
+            self.count = snapshot.snapshot.map_or(0, |snapshot| snapshot.value.len() as u64);
+            Ok(())
+        }
+
+        fn handle_event(&mut self, event: EventSourcedEvent) -> Result<(), String> {
+            self.replayed.lock().unwrap().push(event.sequence);
+            self.count += 1;
+            Ok(())
+        }
+
+        fn handle_command(&mut self, _command: Command, _method: &Method) -> Result<EventSourcedReply, String> {
+            self.count += 1;
+            Ok(EventSourcedReply {
+                events: vec![Any::default()],
+                ..Default::default()
+            })
+        }
+
+        // The count is the length of the snapshot
+        fn snapshot(&self) -> Option<Any> {
+            Some(Any {
+                type_url: String::from("type.googleapis.com/test.Count"),
+                value: vec![0; self.count as usize],
+            })
+        }
+    }
+
+    // The commands of the shopping cart, their fields are not needed
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct AddLineItem {}
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct RemoveLineItem {}
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct GetShoppingCart {}
+    crate::message_names!("com.example.shoppingcart", AddLineItem, RemoveLineItem, GetShoppingCart);
+
+    enum CartCommand {
+        Add(AddLineItem),
+        Remove(RemoveLineItem),
+        Get(GetShoppingCart),
+    }
+
+    struct Counted;
+
+    impl Payload for Counted {
+
+        fn to_any(&self) -> Any {
+            Any::default()
+        }
+    }
+
+    /// Counts the events, emitting one per command, but replies to AddItem with a
+    /// GetShoppingCart instead of an Empty.
+    struct WrongReply;
+
+    impl EventSourcedEntity for WrongReply {
+        type State = u64;
+        type Command = CartCommand;
+        type Event = Counted;
+
+        fn register_types(&self, commands: &mut CommandRegistry<CartCommand>, _events: &mut TypeRegistry<Counted>) {
+            commands
+                .register("AddItem", CartCommand::Add)
+                .register("RemoveItem", CartCommand::Remove)
+                .register("GetCart", CartCommand::Get);
+        }
+
+        fn handle_command(&self, _count: &u64, command: CartCommand, ctx: &mut CommandContext<Counted>) -> Result<Reply, Failure> {
+            ctx.emit(Counted);
+            ctx.effect(SERVICE_NAME.to_string(), "GetCart".to_string(), payload::pack(&GetShoppingCart {}), false);
+            match command {
+                CartCommand::Add(_) => Ok(Reply::new(&GetShoppingCart {})),
+                CartCommand::Remove(_) | CartCommand::Get(_) => Ok(Reply::new(&())),
+            }
+        }
+
+        fn apply_event(&self, count: &mut u64, _event: &Counted) {
+            *count += 1;
+        }
+
+        fn snapshot(&self, count: &u64) -> Option<Any> {
+            Some(Any {
+                type_url: String::from("type.googleapis.com/test.Count"),
+                value: vec![0; *count as usize],
+            })
+        }
+    }
+
+    fn cart_command(id: i64, name: &str, payload: Any) -> event_sourced_stream_in::Message {
+        event_sourced_stream_in::Message::Command(Command {
+            entity_id: "counter-1".to_string(),
+            id,
+            name: name.to_string(),
+            payload: Some(payload),
+            streamed: false,
+        })
+    }
+
+    fn stream(snapshot_every: u16, replayed: &Arc<Mutex<Vec<i64>>>) -> EntityStream {
+        let replayed = replayed.clone();
+        let service = EntityService::new()
+            .persistence_id("counters".to_string())
+            .snapshot(snapshot_every)
+            .handler(move |_| Box::new(Counter { count: 0, replayed: replayed.clone() }) as Box<dyn EventSourcedHandler>)
+            .event_sourced();
+
+        EntityStream::new(fixture::cloudstate(service).entity_options().unwrap())
+    }
+
+    fn send(stream: &mut EntityStream, message: event_sourced_stream_in::Message) -> Option<EventSourcedReply> {
+        match stream.handle_message(EventSourcedStreamIn { message: Some(message) }).unwrap() {
+            Some(out) => match out.message {
+                Some(event_sourced_stream_out::Message::Reply(reply)) => Some(reply),
+                message => panic!("Unexpected message {:?}", message),
+            },
+            None => None,
+        }
+    }
+
+    fn init(stream: &mut EntityStream, snapshot: Option<EventSourcedSnapshot>) {
+        let init = EventSourcedInit {
+            service_name: SERVICE_NAME.to_string(),
+            entity_id: "counter-1".to_string(),
+            snapshot,
+        };
+        assert_eq!(send(stream, event_sourced_stream_in::Message::Init(init)), None);
+    }
+
+    fn event(stream: &mut EntityStream, sequence: i64) {
+        let event = EventSourcedEvent { sequence, payload: Some(Any::default()) };
+        assert_eq!(send(stream, event_sourced_stream_in::Message::Event(event)), None);
+    }
+
+    fn command(stream: &mut EntityStream, id: i64) -> EventSourcedReply {
+        let command = Command {
+            entity_id: "counter-1".to_string(),
+            id,
+            name: "AddItem".to_string(),
+            payload: None,
+            streamed: false,
+        };
+        send(stream, event_sourced_stream_in::Message::Command(command)).expect("reply")
+    }
+
+    fn snapshot_count(reply: &EventSourcedReply) -> Option<usize> {
+        reply.snapshot.as_ref().map(|snapshot| snapshot.value.len())
+    }
+
+    #[test]
+    fn snapshot_every_events() {
+        let replayed = Arc::new(Mutex::new(Vec::new()));
+        let mut stream = stream(3, &replayed);
+        init(&mut stream, None);
+
+        // Replayed events count towards the next snapshot
+        event(&mut stream, 1);
+        assert_eq!(snapshot_count(&command(&mut stream, 1)), None);
+        assert_eq!(snapshot_count(&command(&mut stream, 2)), Some(3));
+
+        assert_eq!(snapshot_count(&command(&mut stream, 3)), None);
+        assert_eq!(snapshot_count(&command(&mut stream, 4)), None);
+        assert_eq!(snapshot_count(&command(&mut stream, 5)), Some(6));
+    }
+
+    #[test]
+    fn no_snapshot_without_snapshot_every() {
+        let replayed = Arc::new(Mutex::new(Vec::new()));
+        let mut stream = stream(0, &replayed);
+        init(&mut stream, None);
+
+        for id in 1..=5 {
+            assert_eq!(snapshot_count(&command(&mut stream, id)), None);
+        }
+    }
+
+    #[test]
+    fn skip_events_contained_in_the_snapshot() {
+        let replayed = Arc::new(Mutex::new(Vec::new()));
+        let mut stream = stream(3, &replayed);
+        let snapshot = EventSourcedSnapshot {
+            snapshot_sequence: 5,
+            snapshot: Some(Any { type_url: String::from("type.googleapis.com/test.Count"), value: vec![0; 5] }),
+        };
+        init(&mut stream, Some(snapshot));
+
+        event(&mut stream, 4);
+        event(&mut stream, 5);
+        event(&mut stream, 6);
+        assert_eq!(*replayed.lock().unwrap(), vec![6]);
+
+        // The skipped events do not count towards the next snapshot
+        assert_eq!(snapshot_count(&command(&mut stream, 1)), None);
+        assert_eq!(snapshot_count(&command(&mut stream, 2)), Some(8));
+    }
+
+    #[test]
+    fn reply_of_another_type_discards_the_events() {
+        let service = EntityService::new()
+            .persistence_id("counters".to_string())
+            .snapshot(1)
+            .entity(WrongReply)
+            .event_sourced();
+        let mut stream = EntityStream::new(fixture::cloudstate(service).entity_options().unwrap());
+        init(&mut stream, None);
+
+        let reply = send(&mut stream, cart_command(1, "AddItem", payload::pack(&AddLineItem {}))).unwrap();
+        match reply.client_action.and_then(|action| action.action) {
+            Some(client_action::Action::Failure(failure)) => assert_eq!(failure.command_id, 1),
+            action => panic!("Unexpected action {:?}", action),
+        }
+        assert!(reply.events.is_empty());
+        assert!(reply.side_effects.is_empty());
+        assert_eq!(reply.snapshot, None);
+
+        // Neither applied to the state nor counted in the sequence
+        let reply = send(&mut stream, cart_command(2, "RemoveItem", payload::pack(&RemoveLineItem {}))).unwrap();
+        assert_eq!(reply.events.len(), 1);
+        assert_eq!(snapshot_count(&reply), Some(1));
+        assert_eq!(stream.sequence, 1);
+    }
+
+    #[test]
+    fn handler_reply_of_another_type_fails_the_stream() {
+        /// Replies to every command with an empty GetShoppingCart.
+        struct Raw;
+
+        impl EventSourcedHandler for Raw {
+
+            fn handle_snapshot(&mut self, _snapshot: EventSourcedSnapshot) -> Result<(), String> {
+                Ok(())
+            }
+
+            fn handle_event(&mut self, _event: EventSourcedEvent) -> Result<(), String> {
+                Ok(())
+            }
+
+            fn handle_command(&mut self, command: Command, _method: &Method) -> Result<EventSourcedReply, String> {
+                Ok(EventSourcedReply {
+                    command_id: command.id,
+                    client_action: Some(ClientAction { action: Some(client_action::Action::Reply(Reply::new(&GetShoppingCart {}))) }),
+                    events: vec![Any::default()],
+                    ..Default::default()
+                })
+            }
+        }
+
+        let service = EntityService::new()
+            .handler(|_| Box::new(Raw) as Box<dyn EventSourcedHandler>)
+            .event_sourced();
+        let mut stream = EntityStream::new(fixture::cloudstate(service).entity_options().unwrap());
+        init(&mut stream, None);
+
+        let command = EventSourcedStreamIn { message: Some(cart_command(1, "AddItem", payload::pack(&AddLineItem {}))) };
+        let failure = stream.handle_message(command).unwrap_err();
+        assert_eq!(failure.command_id, 1);
+        assert_eq!(stream.sequence, 0);
+    }
+
+    #[test]
+    fn method_registered_twice_fails_the_registration() {
+        /// Takes AddItem for RemoveItem.
+        struct Twice;
+
+        impl EventSourcedEntity for Twice {
+            type State = u64;
+            type Command = CartCommand;
+            type Event = Counted;
+
+            fn register_types(&self, commands: &mut CommandRegistry<CartCommand>, _events: &mut TypeRegistry<Counted>) {
+                commands
+                    .register("AddItem", CartCommand::Add)
+                    .register("AddItem", CartCommand::Remove)
+                    .register("GetCart", CartCommand::Get);
+            }
+
+            fn handle_command(&self, _count: &u64, _command: CartCommand, _ctx: &mut CommandContext<Counted>) -> Result<Reply, Failure> {
+                Ok(Reply::new(&()))
+            }
+
+            fn apply_event(&self, _count: &mut u64, _event: &Counted) {}
+        }
+
+        let service = EntityService::new().entity(Twice).event_sourced();
+        match fixture::cloudstate(service).entity_options() {
+            Err(err) => assert!(err.to_string().contains("registers \"AddItem\" more than once"), "{}", err),
+            Ok(_) => panic!("Registered AddItem twice"),
+        }
+    }
+
+    /// Records the fields of the spans enabled at info level, as the default filter of a binary.
+    #[derive(Default)]
+    struct Spans(Arc<Mutex<Vec<(String, Vec<(String, String)>)>>>);
+
+    struct Fields<'a>(&'a mut Vec<(String, String)>);
+
+    impl<'a> Visit for Fields<'a> {
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+
+    impl Subscriber for Spans {
+
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            *metadata.level() <= Level::INFO
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut spans = self.0.lock().unwrap();
+            let mut fields = Vec::new();
+            span.record(&mut Fields(&mut fields));
+            spans.push((span.metadata().name().to_string(), fields));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.0.lock().unwrap();
+            values.record(&mut Fields(&mut spans[span.into_u64() as usize - 1].1));
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn command_span_is_a_child_of_the_trace() {
+        let parent = TraceContext { trace_id: 0x4bf92f3577b34da6a3ce929d0e0e4736, span_id: 0x00f067aa0ba902b7, sampled: true };
+        let spans = Spans::default();
+        let recorded = spans.0.clone();
+
+        tracing::subscriber::with_default(spans, || {
+            let replayed = Arc::new(Mutex::new(Vec::new()));
+            let mut stream = stream(0, &replayed).with_trace(Some(parent));
+            init(&mut stream, None);
+            event(&mut stream, 1);
+            command(&mut stream, 7);
+        });
+
+        // The event span is only enabled at debug level
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.len(), 1);
+        let (name, fields) = &recorded[0];
+        assert_eq!(name, "command");
+        let field = |name: &str| fields.iter().find(|(field, _)| field == name).map(|(_, value)| value.as_str());
+        assert_eq!(field("entity_id"), Some("counter-1"));
+        assert_eq!(field("command_id"), Some("7"));
+        assert_eq!(field("command_name"), Some("AddItem"));
+        assert_eq!(field("trace_id"), Some("4bf92f3577b34da6a3ce929d0e0e4736"));
+        assert_eq!(field("parent_id"), Some("00f067aa0ba902b7"));
+        assert!(field("span_id").is_some());
+    }
+}
@@ -0,0 +1,17 @@
+//! Fixture of the unit tests: the shopping cart protos of the TCK, compiled by build.rs.
+
+use crate::serveless::{CloudState, EntityService};
+
+pub const SERVICE_NAME: &str = "com.example.shoppingcart.ShoppingCart";
+
+/// Descriptor set of the shopping cart protos.
+pub const DESCRIPTOR: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/user-function.desc"));
+
+/// A `CloudState` with `service` registered as the shopping cart.
+pub fn cloudstate(service: EntityService) -> CloudState {
+    let mut cloudstate = CloudState::new();
+    cloudstate
+        .descriptor(DESCRIPTOR)
+        .register_entity_service(SERVICE_NAME.to_string(), service);
+    cloudstate
+}
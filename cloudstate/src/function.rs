@@ -0,0 +1,168 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use futures_core::future::BoxFuture;
+use futures_util::{future, stream, StreamExt};
+use tonic::{Code, Status, Streaming};
+use tracing::{field, info_span, Span};
+use crate::error::Error;
+use crate::handlers::handler::{CommandStream, FunctionHandler};
+use crate::metrics::Metrics;
+use crate::protocol::Options;
+use crate::protocol::spec::function::FunctionCommand;
+use crate::serveless::STATELESS_FUNCTION;
+use crate::trace::TraceContext;
+
+/// Finds the function registered for the command name of a `FunctionCommand`.
+pub fn route(opts: &Options, command: &FunctionCommand) -> Result<Arc<dyn FunctionHandler>, Status> {
+    let service = opts.entity_service(&command.service_name, STATELESS_FUNCTION)
+        .map_err(|err| Status::new(Code::NotFound, err))?;
+
+    service.functions.get(&command.name)
+        .map(|function| function.handler())
+        .ok_or_else(|| Status::unimplemented(format!("No function registered for command {:?}", command.name)))
+}
+
+/// Maps an error returned by a user function to the status sent to the proxy.
+pub fn handler_error(err: String) -> Status {
+    Error::UserHandler(err).into()
+}
+
+/// Span of a function command, a child of the trace context of the call if any.
+pub fn command_span(command: &FunctionCommand, trace: Option<TraceContext>) -> Span {
+    let span = info_span!("command",
+        service_name = %command.service_name, command_name = %command.name,
+        trace_id = field::Empty, span_id = field::Empty, parent_id = field::Empty);
+    if let Some(parent) = trace {
+        parent.enter(&span);
+    }
+    span
+}
+
+/// Measures the handling of a function command, up to the end of its stream for streamed calls.
+pub struct CommandTimer {
+    metrics: Arc<Metrics>,
+    service_name: String,
+    command_name: String,
+    started: Instant,
+}
+
+impl CommandTimer {
+
+    /// Starts measuring `command`, the first one of the stream for a streamed in call.
+    pub fn start(opts: &Options, command: &FunctionCommand) -> Self {
+        CommandTimer {
+            metrics: opts.metrics.clone(),
+            service_name: command.service_name.clone(),
+            command_name: command.name.clone(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Records the command as handled, failed if `result` is an error, and returns `result`.
+    pub fn finish<T, E>(self, result: Result<T, E>) -> Result<T, E> {
+        self.metrics.command_handled(&self.service_name, &self.command_name, self.started.elapsed(), result.is_err());
+        result
+    }
+}
+
+/// Commands of a streamed in call, starting with the `first` one used for routing.
+pub fn command_stream(first: FunctionCommand, rest: Streaming<FunctionCommand>) -> CommandStream {
+    let rest = rest
+        .take_while(|command| {
+            if let Err(status) = command {
+                error!("Error receiving function command from sidecar: {:?}", status);
+            }
+            future::ready(command.is_ok())
+        })
+        .filter_map(|command| future::ready(command.ok()));
+
+    Box::pin(stream::once(future::ready(first)).chain(rest))
+}
+
+/// Polls the future of a handler inside the span of its command.
+pub fn in_span<T>(span: Span, future: BoxFuture<'_, T>) -> InSpan<'_, T> {
+    InSpan { span, future }
+}
+
+/// Future returned by `in_span`.
+pub struct InSpan<'a, T> {
+    span: Span,
+    future: BoxFuture<'a, T>,
+}
+
+impl<'a, T> Future for InSpan<'a, T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let this = &mut *self;
+        let _enter = this.span.enter();
+        this.future.as_mut().poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_types::Any;
+    use tonic::Code;
+    use crate::handlers::handler::FunctionHandler;
+    use crate::protocol::spec::function::{FunctionCommand, FunctionReply};
+    use crate::fixture::{self, SERVICE_NAME};
+    use crate::serveless::EntityService;
+    use super::{handler_error, route};
+
+    struct Echo;
+
+    impl FunctionHandler for Echo {
+        fn handle_unary(&self, command: FunctionCommand) -> Result<FunctionReply, String> {
+            Ok(FunctionReply::reply(command.payload.unwrap_or_default()))
+        }
+    }
+
+    fn command(service_name: &str, name: &str) -> FunctionCommand {
+        FunctionCommand {
+            service_name: service_name.to_string(),
+            name: name.to_string(),
+            payload: Some(Any { type_url: String::from("type.googleapis.com/test"), value: vec![1, 2, 3] }),
+        }
+    }
+
+    #[test]
+    fn route_to_the_registered_function() {
+        let service = EntityService::new().function("GetCart".to_string(), Echo).stateless_function();
+        let opts = fixture::cloudstate(service).entity_options().unwrap();
+
+        let command = command(SERVICE_NAME, "GetCart");
+        let reply = route(&opts, &command).unwrap().handle_unary(command.clone()).unwrap();
+        assert_eq!(reply, FunctionReply::reply(command.payload.unwrap()));
+    }
+
+    #[test]
+    fn route_unknown_commands() {
+        let service = EntityService::new().function("GetCart".to_string(), Echo).stateless_function();
+        let opts = fixture::cloudstate(service).entity_options().unwrap();
+
+        let status = route(&opts, &command(SERVICE_NAME, "AddItem")).err().unwrap();
+        assert_eq!(status.code(), Code::Unimplemented);
+        let status = route(&opts, &command("com.example.Unknown", "GetCart")).err().unwrap();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[test]
+    fn route_only_to_functions() {
+        let service = EntityService::new().function("GetCart".to_string(), Echo).event_sourced();
+        let opts = fixture::cloudstate(service).entity_options().unwrap();
+
+        let status = route(&opts, &command(SERVICE_NAME, "GetCart")).err().unwrap();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[test]
+    fn handler_errors_keep_their_message() {
+        let status = handler_error(String::from("Out of stock"));
+        assert_eq!(status.code(), Code::Unknown);
+        assert_eq!(status.message(), "Out of stock");
+    }
+}
@@ -1,18 +1,206 @@
 pub mod handler {
 
-    pub struct CommandContext;
+    use futures_core::future::BoxFuture;
+    use futures_core::stream::BoxStream;
+    use futures_util::future;
+    use prost_types::Any;
+    use crate::descriptor::Method;
+    use crate::payload::{CommandRegistry, PayloadError, TypeRegistry};
+    use crate::protocol::spec::{Command, Failure, Forward, Reply, SideEffect, StreamCancelled};
+    use crate::protocol::spec::crdt::{
+        CrdtDelta, CrdtReply, CrdtState, CrdtStreamCancelledResponse, CrdtStreamedMessage,
+    };
+    use crate::protocol::spec::eventsourced::{EventSourcedEvent, EventSourcedReply, EventSourcedSnapshot};
+    use crate::protocol::spec::function::{FunctionCommand, FunctionReply};
 
-    pub trait CommandHandler<T> {
-        fn get() -> T;
-        fn handle_command(entity: T, ctx: CommandContext) -> T;
+    /// Conversion of events to their `google.protobuf.Any` payloads, see `payload::pack`.
+    pub trait Payload {
+        fn to_any(&self) -> Any;
     }
 
-    pub trait EventHandler<T> {
-        fn handle_event(entity: T);
+    /// Context of the command being handled.
+    pub struct CommandContext<E> {
+        entity_id: String,
+        id: i64,
+        name: String,
+        streamed: bool,
+        events: Vec<E>,
+        forward: Option<Forward>,
+        side_effects: Vec<SideEffect>,
     }
 
-    pub trait SnapshotHandler<T> {
-        fn snapshot() -> T;
-        fn handle_snapshot(entity: T);
+    impl<E> CommandContext<E> {
+
+        pub fn new(command: &Command) -> Self {
+            CommandContext {
+                entity_id: command.entity_id.clone(),
+                id: command.id,
+                name: command.name.clone(),
+                streamed: command.streamed,
+                events: Vec::new(),
+                forward: None,
+                side_effects: Vec::new(),
+            }
+        }
+
+        pub fn entity_id(&self) -> &str {
+            &self.entity_id
+        }
+
+        pub fn command_id(&self) -> i64 {
+            self.id
+        }
+
+        pub fn command_name(&self) -> &str {
+            &self.name
+        }
+
+        pub fn streamed(&self) -> bool {
+            self.streamed
+        }
+
+        /// Records an event. Events are applied to the state and persisted once the
+        /// command handler returns a reply, and discarded if it fails.
+        pub fn emit(&mut self, event: E) {
+            self.events.push(event);
+        }
+
+        /// Forwards the command to another service. The forward is sent to the client
+        /// instead of the reply returned by the command handler.
+        pub fn forward(&mut self, service_name: String, command_name: String, payload: Any) {
+            self.forward = Some(Forward {
+                service_name,
+                command_name,
+                payload: Some(payload),
+            });
+        }
+
+        /// Adds a side effect, performed once the events are persisted.
+        pub fn effect(&mut self, service_name: String, command_name: String, payload: Any, synchronous: bool) {
+            self.side_effects.push(SideEffect {
+                service_name,
+                command_name,
+                payload: Some(payload),
+                synchronous,
+            });
+        }
+
+        /// Builds the failure to return from the command handler.
+        pub fn fail(&self, description: impl Into<String>) -> Failure {
+            Failure::new(self.id, description)
+        }
+
+        pub(crate) fn into_parts(self) -> (Vec<E>, Option<Forward>, Vec<SideEffect>) {
+            (self.events, self.forward, self.side_effects)
+        }
+    }
+
+    /// An event sourced entity.
+    ///
+    /// The entity itself holds no state: the runtime keeps one `State` per entity id,
+    /// rebuilds it from the snapshot and the persisted events, and hands it to every command.
+    pub trait EventSourcedEntity: Send + Sync + 'static {
+        type State: Default + Send + 'static;
+        type Command: 'static;
+        type Event: Payload + 'static;
+
+        /// Registers the commands by the name of their method, and the payload types of the persisted events.
+        fn register_types(&self, commands: &mut CommandRegistry<Self::Command>, events: &mut TypeRegistry<Self::Event>);
+
+        /// Handles a command. A failure, or a reply of another type than the output type of
+        /// the method, is sent back to the client and the emitted events and side effects are discarded.
+        fn handle_command(&self, state: &Self::State, command: Self::Command, ctx: &mut CommandContext<Self::Event>) -> Result<Reply, Failure>;
+
+        fn apply_event(&self, state: &mut Self::State, event: &Self::Event);
+
+        fn snapshot(&self, _state: &Self::State) -> Option<Any> {
+            None
+        }
+
+        fn restore(&self, snapshot: &Any) -> Result<Self::State, PayloadError> {
+            Err(PayloadError::UnknownType(snapshot.type_url.clone()))
+        }
+    }
+
+    /// Handles the protocol messages of a single event sourced entity instance.
+    /// One handler is created per entity stream opened by the proxy.
+    pub trait EventSourcedHandler: Send {
+        fn handle_snapshot(&mut self, snapshot: EventSourcedSnapshot) -> Result<(), String>;
+        fn handle_event(&mut self, event: EventSourcedEvent) -> Result<(), String>;
+
+        /// Handles a command calling `method`, whose input type the payload was checked against.
+        /// A reply must be of the output type of the method: the stream fails otherwise, and the
+        /// proxy restarts the entity from its persisted events.
+        fn handle_command(&mut self, command: Command, method: &Method) -> Result<EventSourcedReply, String>;
+
+        /// Current state of the entity, requested once `snapshot_every` events were applied.
+        fn snapshot(&self) -> Option<Any> {
+            None
+        }
+    }
+
+    /// Handles the protocol messages of a single CRDT entity instance.
+    /// One handler is created per entity stream opened by the proxy.
+    pub trait CrdtHandler: Send {
+        /// Replaces the current state, either the initial one or a full state from another replica.
+        fn handle_state(&mut self, state: CrdtState) -> Result<(), String>;
+        fn handle_delta(&mut self, delta: CrdtDelta) -> Result<(), String>;
+        fn handle_delete(&mut self) -> Result<(), String>;
+        fn handle_command(&mut self, command: Command) -> Result<CrdtReply, String>;
+        fn handle_stream_cancelled(&mut self, cancelled: StreamCancelled) -> Result<CrdtStreamCancelledResponse, String>;
+
+        /// Messages to push to streamed commands after the state has changed.
+        fn changed(&mut self) -> Vec<CrdtStreamedMessage> {
+            Vec::new()
+        }
+    }
+
+    /// Handles the commands of a stateless function.
+    /// Unary handlers can be plain closures, the streamed variants are opt-in.
+    pub trait FunctionHandler: Send + Sync {
+        fn handle_unary(&self, command: FunctionCommand) -> Result<FunctionReply, String>;
+
+        /// Handles the commands of a streamed in call as they arrive.
+        fn handle_streamed_in(&self, _commands: CommandStream) -> BoxFuture<'_, Result<FunctionReply, String>> {
+            Box::pin(future::ready(Err(String::from("Streamed in calls are not supported by this function"))))
+        }
+
+        /// Handles a streamed out call, sending its replies as they are produced.
+        fn handle_streamed_out(&self, _command: FunctionCommand, _replies: ReplySender) -> BoxFuture<'_, Result<(), String>> {
+            Box::pin(future::ready(Err(String::from("Streamed out calls are not supported by this function"))))
+        }
+
+        /// Handles one command of a bidirectional stream.
+        fn handle_streamed(&self, command: FunctionCommand) -> Result<Vec<FunctionReply>, String> {
+            self.handle_unary(command).map(|reply| vec![reply])
+        }
+    }
+
+    /// Commands of a streamed in call, ending with the call or at the first error receiving them.
+    pub type CommandStream = BoxStream<'static, FunctionCommand>;
+
+    /// Replies of a streamed out call.
+    #[derive(Clone)]
+    pub struct ReplySender(crate::shutdown::ReplySender<FunctionReply>);
+
+    impl ReplySender {
+
+        pub(crate) fn new(sender: crate::shutdown::ReplySender<FunctionReply>) -> Self {
+            ReplySender(sender)
+        }
+
+        /// Sends a reply, fails once the proxy closed the call.
+        pub async fn send(&mut self, reply: FunctionReply) -> Result<(), String> {
+            self.0.send(Ok(reply)).await
+                .map_err(|_| String::from("The proxy closed the function stream"))
+        }
+    }
+
+    impl<F> FunctionHandler for F
+        where F: Fn(FunctionCommand) -> Result<FunctionReply, String> + Send + Sync {
+
+        fn handle_unary(&self, command: FunctionCommand) -> Result<FunctionReply, String> {
+            self(command)
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, watch};
+use tonic::{Code, Request, Response, Status};
+
+pub mod spec {
+    tonic::include_proto!("grpc.health.v1");
+}
+
+pub use self::spec::health_check_response::ServingStatus;
+use self::spec::{HealthCheckRequest, HealthCheckResponse};
+use self::spec::server::Health;
+
+/// Name under which the overall health of the user function is reported.
+pub const OVERALL: &str = "";
+
+#[derive(Debug, Default)]
+struct State {
+    /// False until the proxy discovered the entities, and again once shutting down.
+    ready: bool,
+    services: HashMap<String, ServingStatus>,
+}
+
+/// Serving status of the services reported by `grpc.health.v1.Health`.
+///
+/// All services are `NOT_SERVING` until the proxy has discovered the entities and
+/// during shutdown. In between they are `SERVING` unless user code reports otherwise,
+/// for instance while a downstream dependency is unavailable.
+#[derive(Debug, Clone)]
+pub struct HealthReporter {
+    state: Arc<Mutex<State>>,
+    changes: Arc<watch::Sender<()>>,
+    watcher: watch::Receiver<()>,
+}
+
+impl Default for HealthReporter {
+
+    fn default() -> HealthReporter {
+        let (changes, watcher) = watch::channel(());
+        let mut state = State::default();
+        state.services.insert(String::from(OVERALL), ServingStatus::Serving);
+
+        HealthReporter {
+            state: Arc::new(Mutex::new(state)),
+            changes: Arc::new(changes),
+            watcher,
+        }
+    }
+}
+
+impl HealthReporter {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn set_serving(&self, service_name: &str) {
+        self.set_status(service_name, ServingStatus::Serving);
+    }
+
+    pub fn set_not_serving(&self, service_name: &str) {
+        self.set_status(service_name, ServingStatus::NotServing);
+    }
+
+    /// Sets the status reported for `service_name` once the user function is ready.
+    /// Use `OVERALL` for the user function as a whole.
+    pub fn set_status(&self, service_name: &str, status: ServingStatus) {
+        self.update(|state| {
+            state.services.insert(service_name.to_string(), status);
+        });
+    }
+
+    /// Current status of `service_name`, or `None` for an unknown service.
+    pub fn status(&self, service_name: &str) -> Option<ServingStatus> {
+        let state = self.state.lock().unwrap();
+        state.services.get(service_name).map(|status| {
+            if state.ready { *status } else { ServingStatus::NotServing }
+        })
+    }
+
+    /// Makes `service_name` known, serving unless a status was already set.
+    pub(crate) fn register(&self, service_name: &str) {
+        self.update(|state| {
+            state.services.entry(service_name.to_string()).or_insert(ServingStatus::Serving);
+        });
+    }
+
+    pub(crate) fn set_ready(&self, ready: bool) {
+        self.update(|state| state.ready = ready);
+    }
+
+    fn update<F: FnOnce(&mut State)>(&self, update: F) {
+        update(&mut self.state.lock().unwrap());
+        // Only fails once every receiver is dropped, never while this reporter keeps its own
+        self.changes.broadcast(()).expect("The health reporter keeps a receiver of its changes");
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthService {
+    pub reporter: HealthReporter,
+}
+
+#[tonic::async_trait]
+impl Health for HealthService {
+
+    async fn check(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+
+        let service_name = &request.get_ref().service;
+        match self.reporter.status(service_name) {
+            Some(status) => Ok(Response::new(response(status))),
+            None => Err(Status::new(Code::NotFound, format!("Unknown service {:?}", service_name))),
+        }
+    }
+
+    type WatchStream = mpsc::Receiver<Result<HealthCheckResponse, Status>>;
+
+    async fn watch(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+
+        let service_name = request.into_inner().service;
+        let reporter = self.reporter.clone();
+        let mut changes = self.reporter.watcher.clone();
+        let (mut tx, rx) = mpsc::channel(4);
+
+        tokio::spawn(async move {
+            let mut last = None;
+            loop {
+                let status = reporter.status(&service_name).unwrap_or(ServingStatus::ServiceUnknown);
+                if last != Some(status) {
+                    if tx.send(Ok(response(status))).await.is_err() {
+                        debug!("Health watch of {:?} closed", service_name);
+                        break;
+                    }
+                    last = Some(status);
+                }
+
+                if changes.recv().await.is_none() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(rx))
+    }
+}
+
+fn response(status: ServingStatus) -> HealthCheckResponse {
+    HealthCheckResponse {
+        status: status as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::runtime::Runtime;
+    use tonic::{Code, Request};
+    use crate::fixture::SERVICE_NAME;
+    use super::spec::HealthCheckRequest;
+    use super::spec::server::Health;
+    use super::{HealthReporter, HealthService, ServingStatus, OVERALL};
+
+    fn check(reporter: &HealthReporter, service_name: &str) -> Result<ServingStatus, Code> {
+        let service = HealthService { reporter: reporter.clone() };
+        let request = Request::new(HealthCheckRequest { service: service_name.to_string() });
+        match Runtime::new().unwrap().block_on(service.check(request)) {
+            Ok(response) => match response.into_inner().status {
+                status if status == ServingStatus::Serving as i32 => Ok(ServingStatus::Serving),
+                status if status == ServingStatus::NotServing as i32 => Ok(ServingStatus::NotServing),
+                status => panic!("Unexpected status {}", status),
+            },
+            Err(status) => Err(status.code()),
+        }
+    }
+
+    #[test]
+    fn not_serving_until_ready() {
+        let reporter = HealthReporter::new();
+        reporter.register(SERVICE_NAME);
+        assert_eq!(check(&reporter, OVERALL), Ok(ServingStatus::NotServing));
+        assert_eq!(check(&reporter, SERVICE_NAME), Ok(ServingStatus::NotServing));
+
+        reporter.set_ready(true);
+        assert_eq!(check(&reporter, OVERALL), Ok(ServingStatus::Serving));
+        assert_eq!(check(&reporter, SERVICE_NAME), Ok(ServingStatus::Serving));
+        assert_eq!(check(&reporter, "com.example.Unknown"), Err(Code::NotFound));
+    }
+
+    #[test]
+    fn not_serving_during_shutdown() {
+        let reporter = HealthReporter::new();
+        reporter.register(SERVICE_NAME);
+        reporter.set_ready(true);
+
+        reporter.set_ready(false);
+        assert_eq!(check(&reporter, OVERALL), Ok(ServingStatus::NotServing));
+        // Not even when reported serving by the user code
+        reporter.set_serving(SERVICE_NAME);
+        assert_eq!(check(&reporter, SERVICE_NAME), Ok(ServingStatus::NotServing));
+    }
+
+    #[test]
+    fn status_reported_once_ready() {
+        let reporter = HealthReporter::new();
+        reporter.set_not_serving(SERVICE_NAME);
+        // Registering keeps the reported status
+        reporter.register(SERVICE_NAME);
+        reporter.set_ready(true);
+
+        assert_eq!(check(&reporter, SERVICE_NAME), Ok(ServingStatus::NotServing));
+        assert_eq!(check(&reporter, OVERALL), Ok(ServingStatus::Serving));
+        reporter.set_serving(SERVICE_NAME);
+        assert_eq!(check(&reporter, SERVICE_NAME), Ok(ServingStatus::Serving));
+    }
+}
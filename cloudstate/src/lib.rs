@@ -1,10 +1,32 @@
 #[macro_use]
 extern crate log;
-extern crate log4rs;
 
 pub mod protocol;
 pub mod serveless;
 pub mod handlers;
+pub mod eventsourced;
+pub mod crdt;
+pub mod function;
+pub mod payload;
+#[cfg(any(test, feature = "build"))]
+pub mod build;
+pub mod descriptor;
+pub mod error;
+pub mod health;
+pub mod listener;
+pub mod metrics;
+pub mod reflection;
+pub mod settings;
+pub mod shutdown;
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
+pub mod tls;
+pub mod trace;
+
+#[cfg(test)]
+mod fixture;
+
+pub use crate::error::Error;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,177 @@
+use std::env;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use crate::error::Error;
+
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Where the user function listens for the proxy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Address {
+    Tcp(SocketAddr),
+    /// A unix domain socket, for proxies running on the same host.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl fmt::Display for Address {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Tcp(addr) => write!(f, "{}", addr),
+            #[cfg(unix)]
+            Address::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Resolves the TCP address to bind. Unset host and port are read from the `HOST`
+/// and `PORT` environment variables, defaulting to all interfaces on port 8080.
+/// `HOST` may be an IP address or a hostname, resolved to its first address.
+pub fn tcp_address(host: Option<IpAddr>, port: Option<u16>) -> Result<Address, String> {
+    address_from(host, port, |name| env::var(name).ok())
+}
+
+// The environment is read through `var`, so that the tests need not change the process one
+fn address_from(host: Option<IpAddr>, port: Option<u16>, var: impl Fn(&str) -> Option<String>) -> Result<Address, String> {
+    let port = match port {
+        Some(port) => port,
+        None => match var("PORT") {
+            Some(value) => value.trim().parse()
+                .map_err(|_| format!("Invalid PORT environment variable {:?}", value))?,
+            None => DEFAULT_PORT,
+        },
+    };
+    let host = match host {
+        Some(host) => host,
+        None => match var("HOST") {
+            Some(name) => resolve(name.trim(), port)?,
+            None => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        },
+    };
+
+    Ok(Address::Tcp(SocketAddr::new(host, port)))
+}
+
+/// Removes the socket left at `path` by a previous run before binding it again.
+/// Any other kind of file is kept, and fails the start.
+#[cfg(unix)]
+pub fn remove_stale_socket(path: &Path) -> Result<(), Error> {
+    use std::fs;
+    use std::io;
+    use std::os::unix::fs::FileTypeExt;
+
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => Ok(fs::remove_file(path)?),
+        Ok(_) => Err(Error::Config(format!("Unable to listen on {}, it exists and is not a socket", path.display()))),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn resolve(host: &str, port: u16) -> Result<IpAddr, String> {
+    if let Ok(ip) = host.parse() {
+        return Ok(ip);
+    }
+
+    (host, port).to_socket_addrs()
+        .map_err(|err| format!("Unable to resolve the HOST environment variable {:?}: {}", host, err))?
+        .next()
+        .map(|addr| addr.ip())
+        .ok_or_else(|| format!("The HOST environment variable {:?} resolves to no address", host))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::net::SocketAddr;
+    use super::{address_from, tcp_address, Address};
+
+    fn address(host: Option<&str>, port: Option<u16>, vars: &[(&str, &str)]) -> Result<Address, String> {
+        let var = |name: &str| vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.to_string());
+        address_from(host.map(|host| host.parse().unwrap()), port, var)
+    }
+
+    fn tcp(addr: &str) -> Result<Address, String> {
+        Ok(Address::Tcp(addr.parse::<SocketAddr>().unwrap()))
+    }
+
+    #[test]
+    fn all_interfaces_on_8080_by_default() {
+        assert_eq!(address(None, None, &[]), tcp("0.0.0.0:8080"));
+    }
+
+    #[test]
+    fn host_and_port_from_the_environment() {
+        assert_eq!(address(None, None, &[("PORT", "9000")]), tcp("0.0.0.0:9000"));
+        assert_eq!(address(None, None, &[("HOST", "127.0.0.1")]), tcp("127.0.0.1:8080"));
+        assert_eq!(address(None, None, &[("HOST", "::1"), ("PORT", " 9000 ")]), tcp("[::1]:9000"));
+        assert_eq!(address(None, None, &[("HOST", "localhost")]).map(|addr| addr.to_string().ends_with(":8080")), Ok(true));
+    }
+
+    #[test]
+    fn explicit_host_and_port_override_the_environment() {
+        let vars = [("HOST", "::1"), ("PORT", "9000")];
+        assert_eq!(address(Some("127.0.0.1"), Some(8000), &vars), tcp("127.0.0.1:8000"));
+        assert_eq!(address(Some("::"), None, &vars), tcp("[::]:9000"));
+        assert_eq!(address(None, Some(8000), &vars), tcp("[::1]:8000"));
+    }
+
+    #[test]
+    fn reject_an_invalid_port() {
+        for port in &["http", "65536", "-1", ""] {
+            match address(None, None, &[("PORT", port)]) {
+                Err(message) => assert!(message.contains("Invalid PORT environment variable"), "{}", message),
+                other => panic!("Unexpected address {:?} for PORT {:?}", other, port),
+            }
+        }
+        // Only read when no port is set
+        assert_eq!(address(None, Some(8000), &[("PORT", "http")]), tcp("0.0.0.0:8000"));
+    }
+
+    // The only test changing the process environment, the other tests read theirs through
+    // address_from. Only sets valid values, other tests may resolve an address meanwhile
+    #[test]
+    fn tcp_address_reads_the_process_environment() {
+        env::set_var("HOST", "127.0.0.1");
+        env::set_var("PORT", "9000");
+        let from_env = tcp_address(None, None);
+        let explicit = tcp_address(Some("::1".parse().unwrap()), Some(8000));
+        env::remove_var("HOST");
+        env::remove_var("PORT");
+
+        assert_eq!(from_env, tcp("127.0.0.1:9000"));
+        assert_eq!(explicit, tcp("[::1]:8000"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn remove_stale_socket_removes_sockets_only() {
+        use std::fs;
+        use std::os::unix::net::UnixListener;
+        use std::path::PathBuf;
+        use crate::error::Error;
+        use super::remove_stale_socket;
+
+        let path = |name: &str| -> PathBuf { env::temp_dir().join(format!("cloudstate-{}-{}", name, std::process::id())) };
+        let socket = path("stale.sock");
+        drop(UnixListener::bind(&socket).unwrap());
+        remove_stale_socket(&socket).unwrap();
+        assert!(!socket.exists());
+
+        // Nothing to remove
+        remove_stale_socket(&socket).unwrap();
+
+        let file = path("regular.sock");
+        fs::write(&file, b"data").unwrap();
+        match remove_stale_socket(&file) {
+            Err(Error::Config(message)) => assert!(message.contains("is not a socket"), "{}", message),
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert!(file.exists());
+        fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,396 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Counters of the user function, shared by all the gRPC services.
+///
+/// With the `prometheus` feature they are also exported, per service and command
+/// name, on the HTTP endpoint set with `CloudState::metrics_address`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    reported_errors: AtomicU64,
+    recorder: recorder::Recorder,
+}
+
+impl Metrics {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn error_reported(&self) {
+        self.reported_errors.fetch_add(1, Ordering::Relaxed);
+        self.recorder.error_reported();
+    }
+
+    /// Number of errors reported by the proxy through `EntityDiscovery.reportError`.
+    pub fn reported_errors(&self) -> u64 {
+        self.reported_errors.load(Ordering::Relaxed)
+    }
+
+    pub fn command_handled(&self, service_name: &str, command_name: &str, latency: Duration, failed: bool) {
+        self.recorder.command_handled(service_name, command_name, latency, failed);
+    }
+
+    pub fn events_emitted(&self, service_name: &str, count: usize) {
+        if count > 0 {
+            self.recorder.events_emitted(service_name, count);
+        }
+    }
+
+    pub fn snapshot_written(&self, service_name: &str) {
+        self.recorder.snapshot_written(service_name);
+    }
+
+    /// Time between the `EventSourcedInit` of an entity and its first command,
+    /// spent restoring the snapshot and replaying the events.
+    pub fn replay_finished(&self, service_name: &str, duration: Duration) {
+        self.recorder.replay_finished(service_name, duration);
+    }
+
+    pub fn crdt_delta_received(&self, service_name: &str) {
+        self.recorder.crdt_delta_received(service_name);
+    }
+
+    pub fn crdt_delta_sent(&self, service_name: &str) {
+        self.recorder.crdt_delta_sent(service_name);
+    }
+
+    /// Counts an entity stream as active until the returned guard is dropped.
+    pub fn stream_opened(self: &Arc<Self>, entity_type: &str) -> ActiveStream {
+        self.recorder.streams_changed(entity_type, 1);
+        ActiveStream {
+            metrics: self.clone(),
+            entity_type: entity_type.to_string(),
+        }
+    }
+}
+
+/// Guard of an open entity stream, see `Metrics::stream_opened`.
+#[derive(Debug)]
+pub struct ActiveStream {
+    metrics: Arc<Metrics>,
+    entity_type: String,
+}
+
+impl Drop for ActiveStream {
+
+    fn drop(&mut self) {
+        self.metrics.recorder.streams_changed(&self.entity_type, -1);
+    }
+}
+
+#[cfg(feature = "prometheus")]
+pub use self::recorder::serve;
+
+#[cfg(feature = "prometheus")]
+mod recorder {
+    use std::convert::Infallible;
+    use std::fmt;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use futures_util::future;
+    use hyper::{Body, Response};
+    use hyper::service::{make_service_fn, service_fn};
+    use prometheus::{
+        Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts,
+        Registry, TextEncoder,
+    };
+    use crate::error::Error;
+    use super::Metrics;
+
+    pub struct Recorder {
+        pub(super) registry: Registry,
+        reported_errors: IntCounter,
+        commands: IntCounterVec,
+        command_failures: IntCounterVec,
+        command_latency: HistogramVec,
+        events: IntCounterVec,
+        snapshots: IntCounterVec,
+        replay_duration: HistogramVec,
+        active_streams: IntGaugeVec,
+        crdt_deltas_received: IntCounterVec,
+        crdt_deltas_sent: IntCounterVec,
+    }
+
+    impl fmt::Debug for Recorder {
+
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("Recorder")
+        }
+    }
+
+    impl Default for Recorder {
+
+        fn default() -> Recorder {
+            let registry = Registry::new_custom(Some(String::from("cloudstate")), None)
+                .expect("metrics registry");
+            let recorder = Recorder {
+                reported_errors: IntCounter::new("reported_errors_total", "Errors reported by the proxy")
+                    .expect("metric"),
+                commands: counter("commands_total", "Commands handled", &["service", "command"]),
+                command_failures: counter("command_failures_total", "Commands answered with a failure", &["service", "command"]),
+                command_latency: histogram("command_duration_seconds", "Time spent handling a command", &["service", "command"]),
+                events: counter("events_emitted_total", "Events emitted by commands", &["service"]),
+                snapshots: counter("snapshots_written_total", "Snapshots sent to the proxy", &["service"]),
+                replay_duration: histogram("replay_duration_seconds", "Time spent restoring an entity before its first command", &["service"]),
+                active_streams: IntGaugeVec::new(Opts::new("active_streams", "Entity streams currently open"), &["entity_type"])
+                    .expect("metric"),
+                crdt_deltas_received: counter("crdt_deltas_received_total", "CRDT deltas received from other replicas", &["service"]),
+                crdt_deltas_sent: counter("crdt_deltas_sent_total", "CRDT state changes sent to the proxy", &["service"]),
+                registry,
+            };
+            recorder.register();
+            recorder
+        }
+    }
+
+    impl Recorder {
+
+        fn register(&self) {
+            let registry = &self.registry;
+            // Names are unique, registering can not fail
+            registry.register(Box::new(self.reported_errors.clone())).expect("register metric");
+            registry.register(Box::new(self.commands.clone())).expect("register metric");
+            registry.register(Box::new(self.command_failures.clone())).expect("register metric");
+            registry.register(Box::new(self.command_latency.clone())).expect("register metric");
+            registry.register(Box::new(self.events.clone())).expect("register metric");
+            registry.register(Box::new(self.snapshots.clone())).expect("register metric");
+            registry.register(Box::new(self.replay_duration.clone())).expect("register metric");
+            registry.register(Box::new(self.active_streams.clone())).expect("register metric");
+            registry.register(Box::new(self.crdt_deltas_received.clone())).expect("register metric");
+            registry.register(Box::new(self.crdt_deltas_sent.clone())).expect("register metric");
+        }
+
+        pub fn error_reported(&self) {
+            self.reported_errors.inc();
+        }
+
+        pub fn command_handled(&self, service_name: &str, command_name: &str, latency: Duration, failed: bool) {
+            let labels = [service_name, command_name];
+            self.commands.with_label_values(&labels).inc();
+            if failed {
+                self.command_failures.with_label_values(&labels).inc();
+            }
+            self.command_latency.with_label_values(&labels).observe(seconds(latency));
+        }
+
+        pub fn events_emitted(&self, service_name: &str, count: usize) {
+            self.events.with_label_values(&[service_name]).inc_by(count as i64);
+        }
+
+        pub fn snapshot_written(&self, service_name: &str) {
+            self.snapshots.with_label_values(&[service_name]).inc();
+        }
+
+        pub fn replay_finished(&self, service_name: &str, duration: Duration) {
+            self.replay_duration.with_label_values(&[service_name]).observe(seconds(duration));
+        }
+
+        pub fn crdt_delta_received(&self, service_name: &str) {
+            self.crdt_deltas_received.with_label_values(&[service_name]).inc();
+        }
+
+        pub fn crdt_delta_sent(&self, service_name: &str) {
+            self.crdt_deltas_sent.with_label_values(&[service_name]).inc();
+        }
+
+        pub fn streams_changed(&self, entity_type: &str, delta: i64) {
+            self.active_streams.with_label_values(&[entity_type]).add(delta);
+        }
+
+        fn encode(&self) -> (Vec<u8>, String) {
+            let encoder = TextEncoder::new();
+            let mut buffer = Vec::new();
+            if let Err(err) = encoder.encode(&self.registry.gather(), &mut buffer) {
+                error!("Unable to encode the metrics. Error: {:?}", err);
+            }
+            (buffer, encoder.format_type().to_string())
+        }
+    }
+
+    fn counter(name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+        IntCounterVec::new(Opts::new(name, help), labels).expect("metric")
+    }
+
+    fn histogram(name: &str, help: &str, labels: &[&str]) -> HistogramVec {
+        HistogramVec::new(HistogramOpts::new(name, help), labels).expect("metric")
+    }
+
+    fn seconds(duration: Duration) -> f64 {
+        duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
+    }
+
+    /// Serves the metrics in the Prometheus text format on every path of `addr`.
+    pub async fn serve(addr: SocketAddr, metrics: Arc<Metrics>) -> Result<(), Error> {
+        info!("Serving Prometheus metrics on http://{}/metrics", addr);
+        let make_service = make_service_fn(move |_| {
+            let metrics = metrics.clone();
+            future::ok::<_, Infallible>(service_fn(move |_request| {
+                let (body, content_type) = metrics.recorder.encode();
+                let response = Response::builder()
+                    .header(hyper::header::CONTENT_TYPE, content_type)
+                    .body(Body::from(body))
+                    .expect("metrics response");
+                future::ok::<_, Infallible>(response)
+            }))
+        });
+
+        hyper::Server::bind(&addr).serve(make_service).await?;
+        Ok(())
+    }
+}
+
+/// Without the `prometheus` feature only the reported errors are counted.
+#[cfg(not(feature = "prometheus"))]
+mod recorder {
+    use std::time::Duration;
+
+    #[derive(Debug, Default)]
+    pub struct Recorder;
+
+    impl Recorder {
+
+        pub fn error_reported(&self) {}
+
+        pub fn command_handled(&self, _service_name: &str, _command_name: &str, _latency: Duration, _failed: bool) {}
+
+        pub fn events_emitted(&self, _service_name: &str, _count: usize) {}
+
+        pub fn snapshot_written(&self, _service_name: &str) {}
+
+        pub fn replay_finished(&self, _service_name: &str, _duration: Duration) {}
+
+        pub fn crdt_delta_received(&self, _service_name: &str) {}
+
+        pub fn crdt_delta_sent(&self, _service_name: &str) {}
+
+        pub fn streams_changed(&self, _entity_type: &str, _delta: i64) {}
+    }
+}
+
+#[cfg(all(test, feature = "prometheus"))]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+    use prometheus::proto::Metric;
+    use tokio::runtime::Runtime;
+    use crate::fixture::SERVICE_NAME;
+    use super::{serve, Metrics};
+
+    // The metric of `name` labelled with exactly `labels`
+    fn metric(metrics: &Metrics, name: &str, labels: &[(&str, &str)]) -> Option<Metric> {
+        metrics.recorder.registry.gather().into_iter()
+            .find(|family| family.get_name() == format!("cloudstate_{}", name))?
+            .get_metric().iter()
+            .find(|metric| {
+                let pairs: Vec<(&str, &str)> = metric.get_label().iter()
+                    .map(|label| (label.get_name(), label.get_value()))
+                    .collect();
+                labels.iter().all(|label| pairs.contains(label)) && pairs.len() == labels.len()
+            })
+            .cloned()
+    }
+
+    fn counter(metrics: &Metrics, name: &str, labels: &[(&str, &str)]) -> f64 {
+        metric(metrics, name, labels).map_or(0.0, |metric| metric.get_counter().get_value())
+    }
+
+    fn gauge(metrics: &Metrics, entity_type: &str) -> f64 {
+        metric(metrics, "active_streams", &[("entity_type", entity_type)])
+            .map_or(0.0, |metric| metric.get_gauge().get_value())
+    }
+
+    fn samples(metrics: &Metrics, name: &str, labels: &[(&str, &str)]) -> u64 {
+        metric(metrics, name, labels).map_or(0, |metric| metric.get_histogram().get_sample_count())
+    }
+
+    #[test]
+    fn record_commands_per_service_and_command() {
+        let metrics = Metrics::new();
+        metrics.command_handled(SERVICE_NAME, "AddItem", Duration::from_millis(2), false);
+        metrics.command_handled(SERVICE_NAME, "AddItem", Duration::from_millis(3), true);
+        metrics.command_handled(SERVICE_NAME, "GetCart", Duration::from_millis(1), false);
+
+        let add = [("service", SERVICE_NAME), ("command", "AddItem")];
+        let get = [("service", SERVICE_NAME), ("command", "GetCart")];
+        assert_eq!(counter(&metrics, "commands_total", &add), 2.0);
+        assert_eq!(counter(&metrics, "command_failures_total", &add), 1.0);
+        assert_eq!(counter(&metrics, "commands_total", &get), 1.0);
+        assert_eq!(counter(&metrics, "command_failures_total", &get), 0.0);
+        assert_eq!(samples(&metrics, "command_duration_seconds", &add), 2);
+        let sum = metric(&metrics, "command_duration_seconds", &add).unwrap().get_histogram().get_sample_sum();
+        assert!((sum - 0.005).abs() < 1e-9, "{}", sum);
+    }
+
+    #[test]
+    fn record_events_snapshots_replays_and_deltas_per_service() {
+        let metrics = Metrics::new();
+        metrics.events_emitted(SERVICE_NAME, 3);
+        metrics.events_emitted(SERVICE_NAME, 0);
+        metrics.events_emitted("com.example.Counter", 1);
+        metrics.snapshot_written(SERVICE_NAME);
+        metrics.replay_finished(SERVICE_NAME, Duration::from_millis(5));
+        metrics.crdt_delta_received("com.example.Counter");
+        metrics.crdt_delta_sent("com.example.Counter");
+        metrics.crdt_delta_sent("com.example.Counter");
+        metrics.error_reported();
+
+        let cart = [("service", SERVICE_NAME)];
+        let counter_service = [("service", "com.example.Counter")];
+        assert_eq!(counter(&metrics, "events_emitted_total", &cart), 3.0);
+        assert_eq!(counter(&metrics, "events_emitted_total", &counter_service), 1.0);
+        assert_eq!(counter(&metrics, "snapshots_written_total", &cart), 1.0);
+        assert_eq!(samples(&metrics, "replay_duration_seconds", &cart), 1);
+        assert_eq!(counter(&metrics, "crdt_deltas_received_total", &counter_service), 1.0);
+        assert_eq!(counter(&metrics, "crdt_deltas_sent_total", &counter_service), 2.0);
+        assert_eq!(counter(&metrics, "crdt_deltas_sent_total", &cart), 0.0);
+        assert_eq!(counter(&metrics, "reported_errors_total", &[]), 1.0);
+        assert_eq!(metrics.reported_errors(), 1);
+    }
+
+    #[test]
+    fn active_streams_until_dropped() {
+        let metrics = Arc::new(Metrics::new());
+        let first = metrics.stream_opened("cloudstate.eventsourced.EventSourced");
+        let second = metrics.stream_opened("cloudstate.eventsourced.EventSourced");
+        let crdt = metrics.stream_opened("cloudstate.crdt.Crdt");
+        assert_eq!(gauge(&metrics, "cloudstate.eventsourced.EventSourced"), 2.0);
+        assert_eq!(gauge(&metrics, "cloudstate.crdt.Crdt"), 1.0);
+
+        drop(first);
+        assert_eq!(gauge(&metrics, "cloudstate.eventsourced.EventSourced"), 1.0);
+        drop(second);
+        drop(crdt);
+        assert_eq!(gauge(&metrics, "cloudstate.eventsourced.EventSourced"), 0.0);
+        assert_eq!(gauge(&metrics, "cloudstate.crdt.Crdt"), 0.0);
+    }
+
+    #[test]
+    fn serve_the_text_format() {
+        let metrics = Arc::new(Metrics::new());
+        metrics.command_handled(SERVICE_NAME, "AddItem", Duration::from_millis(2), false);
+
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let runtime = Runtime::new().unwrap();
+        let served = metrics.clone();
+        runtime.spawn(async move { serve(addr, served).await.unwrap() });
+
+        let mut stream = (0..100)
+            .find_map(|_| TcpStream::connect(addr).map_err(|_| thread::sleep(Duration::from_millis(10))).ok())
+            .expect("metrics server listening");
+        stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.contains("content-type: text/plain; version=0.0.4"), "{}", response);
+        assert!(response.contains("# TYPE cloudstate_commands_total counter"), "{}", response);
+        let line = format!("cloudstate_commands_total{{command=\"AddItem\",service=\"{}\"}} 1", SERVICE_NAME);
+        assert!(response.contains(&line), "{}", response);
+    }
+}
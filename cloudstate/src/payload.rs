@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+use std::fmt;
+use prost::{DecodeError, Message};
+use prost_types::{Any, DescriptorProto, FileDescriptorSet};
+
+pub const TYPE_URL_PREFIX: &str = "type.googleapis.com/";
+
+/// Error raised when a `google.protobuf.Any` payload can not be turned into a message.
+#[derive(Debug)]
+pub enum PayloadError {
+    /// The message carries no payload at all.
+    Missing,
+    /// No decoder is registered for the type url.
+    UnknownType(String),
+    /// No command is registered for the method.
+    UnknownMethod(String),
+    /// The payload is of another type than the expected one.
+    UnexpectedType { expected: String, actual: String },
+    /// The payload bytes do not decode as the registered type.
+    Decode { type_url: String, error: DecodeError },
+}
+
+impl fmt::Display for PayloadError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadError::Missing => write!(f, "Missing payload"),
+            PayloadError::UnknownType(type_url) => write!(f, "Unknown payload type {:?}", type_url),
+            PayloadError::UnknownMethod(method) => write!(f, "No command registered for method {:?}", method),
+            PayloadError::UnexpectedType { expected, actual } =>
+                write!(f, "Expected payload of type {:?} but got {:?}", expected, actual),
+            PayloadError::Decode { type_url, error } =>
+                write!(f, "Unable to decode payload of type {:?}: {}", type_url, error),
+        }
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+/// A protobuf message knowing its fully qualified name, implemented from the descriptor set by
+/// `build::message_names`, or by hand with `message_names!`.
+pub trait MessageName: Message {
+    /// Name of the message prefixed by its package, e.g. `com.example.shoppingcart.AddLineItem`.
+    const FULL_NAME: &'static str;
+
+    fn type_url() -> String {
+        type_url(Self::FULL_NAME)
+    }
+}
+
+/// `google.protobuf.Empty`, as decoded by prost.
+impl MessageName for () {
+    const FULL_NAME: &'static str = "google.protobuf.Empty";
+}
+
+/// Includes the `MessageName` implementations written by `build::message_names` for a protobuf
+/// package, next to the messages generated by prost.
+///
+/// ```ignore
+/// pub mod shoppingcart {
+///     include!(concat!(env!("OUT_DIR"), "/com.example.shoppingcart.rs"));
+///     cloudstate::include_message_names!("com.example.shoppingcart");
+/// }
+/// ```
+#[macro_export]
+macro_rules! include_message_names {
+    ($package:expr) => {
+        include!(concat!(env!("OUT_DIR"), "/", $package, ".names.rs"));
+    };
+}
+
+/// Implements `MessageName` for messages of the given protobuf package that are not generated
+/// from a descriptor set, see `include_message_names!` otherwise.
+///
+/// ```ignore
+/// cloudstate::message_names!("com.example.shoppingcart", AddLineItem, RemoveLineItem, GetShoppingCart, Cart);
+/// ```
+#[macro_export]
+macro_rules! message_names {
+    ($package:expr, $($message:ident),+ $(,)?) => {
+        $(
+            impl $crate::payload::MessageName for $message {
+                const FULL_NAME: &'static str = concat!($package, ".", stringify!($message));
+            }
+        )+
+    };
+}
+
+pub fn type_url(full_name: &str) -> String {
+    format!("{}{}", TYPE_URL_PREFIX, full_name)
+}
+
+/// Serializes a message.
+pub fn encode<M: Message>(message: &M) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(message.encoded_len());
+    // Encoding into a Vec can not run out of capacity
+    message.encode(&mut bytes).expect("encode message");
+    bytes
+}
+
+/// Packs a message into a `google.protobuf.Any`.
+pub fn pack<M: MessageName>(message: &M) -> Any {
+    Any {
+        type_url: M::type_url(),
+        value: encode(message),
+    }
+}
+
+/// Unpacks a `google.protobuf.Any` holding a message of type `M`.
+pub fn unpack<M: MessageName + Default>(any: &Any) -> Result<M, PayloadError> {
+    let expected = M::type_url();
+    if any.type_url != expected {
+        return Err(PayloadError::UnexpectedType { expected, actual: any.type_url.clone() });
+    }
+
+    M::decode(&any.value[..])
+        .map_err(|error| PayloadError::Decode { type_url: expected, error })
+}
+
+/// Fully qualified names of all the messages declared in a serialized `FileDescriptorSet`.
+pub fn message_names(descriptor_set: &[u8]) -> Result<Vec<String>, DecodeError> {
+    let descriptors = FileDescriptorSet::decode(descriptor_set)?;
+
+    let mut names = Vec::new();
+    for file in descriptors.file {
+        let package = file.package.unwrap_or_default();
+        for message in &file.message_type {
+            collect_names(&package, message, &mut names);
+        }
+    }
+    Ok(names)
+}
+
+fn collect_names(scope: &str, message: &DescriptorProto, names: &mut Vec<String>) {
+    let name = match message.name.as_ref() {
+        Some(name) if scope.is_empty() => name.to_string(),
+        Some(name) => format!("{}.{}", scope, name),
+        None => return,
+    };
+
+    for nested in &message.nested_type {
+        collect_names(&name, nested, names);
+    }
+    names.push(name);
+}
+
+type Decoder<T> = Box<dyn Fn(&[u8]) -> Result<T, DecodeError> + Send + Sync>;
+
+/// Maps type urls to decoders turning the payloads into values of `T`,
+/// usually the enum of the events of an entity.
+pub struct TypeRegistry<T> {
+    decoders: HashMap<String, Decoder<T>>,
+    duplicates: Vec<String>,
+}
+
+impl<T> Default for TypeRegistry<T> {
+
+    fn default() -> TypeRegistry<T> {
+        TypeRegistry {
+            decoders: HashMap::new(),
+            duplicates: Vec::new(),
+        }
+    }
+}
+
+impl<T> TypeRegistry<T> {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers the message `M`, converted into `T` by `into`. A type registered
+    /// twice keeps its first conversion and is reported by `duplicates`.
+    pub fn register<M, F>(&mut self, into: F) -> &mut TypeRegistry<T>
+        where M: MessageName + Default + 'static,
+              F: Fn(M) -> T + Send + Sync + 'static {
+        let type_url = M::type_url();
+        if self.decoders.contains_key(&type_url) {
+            self.duplicates.push(type_url);
+            return self;
+        }
+
+        let decoder = move |bytes: &[u8]| M::decode(bytes).map(&into);
+        self.decoders.insert(type_url, Box::new(decoder));
+        self
+    }
+
+    pub fn type_urls(&self) -> impl Iterator<Item = &String> {
+        self.decoders.keys()
+    }
+
+    /// Type urls registered more than once.
+    pub fn duplicates(&self) -> &[String] {
+        &self.duplicates
+    }
+
+    pub fn unpack(&self, any: &Any) -> Result<T, PayloadError> {
+        let decoder = self.decoders.get(&any.type_url)
+            .ok_or_else(|| PayloadError::UnknownType(any.type_url.clone()))?;
+
+        decoder(&any.value[..])
+            .map_err(|error| PayloadError::Decode { type_url: any.type_url.clone(), error })
+    }
+}
+
+/// Maps the methods of a service to decoders turning the command payloads into values
+/// of `T`, usually the enum of the commands of an entity. Unlike a `TypeRegistry`,
+/// several methods may take the same message.
+pub struct CommandRegistry<T> {
+    commands: HashMap<String, (String, Decoder<T>)>,
+    duplicates: Vec<String>,
+}
+
+impl<T> Default for CommandRegistry<T> {
+
+    fn default() -> CommandRegistry<T> {
+        CommandRegistry {
+            commands: HashMap::new(),
+            duplicates: Vec::new(),
+        }
+    }
+}
+
+impl<T> CommandRegistry<T> {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers the command of the method named `method`, taking the message `M` converted
+    /// into `T` by `into`. A method registered twice keeps its first command and is reported
+    /// by `duplicates`.
+    pub fn register<M, F>(&mut self, method: &str, into: F) -> &mut CommandRegistry<T>
+        where M: MessageName + Default + 'static,
+              F: Fn(M) -> T + Send + Sync + 'static {
+        if self.commands.contains_key(method) {
+            self.duplicates.push(method.to_string());
+            return self;
+        }
+
+        let decoder = move |bytes: &[u8]| M::decode(bytes).map(&into);
+        self.commands.insert(method.to_string(), (M::type_url(), Box::new(decoder)));
+        self
+    }
+
+    /// Names of the registered methods with the type url of their command.
+    pub fn methods(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.commands.iter().map(|(method, (type_url, _))| (method, type_url))
+    }
+
+    /// Methods registered more than once.
+    pub fn duplicates(&self) -> &[String] {
+        &self.duplicates
+    }
+
+    /// Decodes the payload of a command calling `method`.
+    pub fn unpack(&self, method: &str, any: &Any) -> Result<T, PayloadError> {
+        let (type_url, decoder) = self.commands.get(method)
+            .ok_or_else(|| PayloadError::UnknownMethod(method.to_string()))?;
+        if any.type_url != *type_url {
+            return Err(PayloadError::UnexpectedType { expected: type_url.clone(), actual: any.type_url.clone() });
+        }
+
+        decoder(&any.value[..])
+            .map_err(|error| PayloadError::Decode { type_url: any.type_url.clone(), error })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_types::{Any, Duration};
+    use super::{pack, unpack, CommandRegistry, MessageName, PayloadError, TypeRegistry};
+
+    impl MessageName for Duration {
+        const FULL_NAME: &'static str = "google.protobuf.Duration";
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Value {
+        Duration(Duration),
+        Empty,
+    }
+
+    fn registry() -> TypeRegistry<Value> {
+        let mut registry = TypeRegistry::new();
+        registry
+            .register(Value::Duration)
+            .register(|()| Value::Empty);
+        registry
+    }
+
+    #[test]
+    fn unpack_registered_types() {
+        let duration = Duration { seconds: 3, nanos: 5 };
+        assert_eq!(registry().unpack(&pack(&duration)).unwrap(), Value::Duration(duration));
+        assert_eq!(registry().unpack(&pack(&())).unwrap(), Value::Empty);
+    }
+
+    #[test]
+    fn unpack_unknown_type_url() {
+        let any = Any {
+            type_url: String::from("type.googleapis.com/google.protobuf.Timestamp"),
+            value: Vec::new(),
+        };
+        match registry().unpack(&any) {
+            Err(PayloadError::UnknownType(type_url)) => assert_eq!(type_url, any.type_url),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unpack_undecodable_payload() {
+        let any = Any {
+            type_url: Duration::type_url(),
+            value: vec![0xff],
+        };
+        match registry().unpack(&any) {
+            Err(PayloadError::Decode { type_url, .. }) => assert_eq!(type_url, any.type_url),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unpack_other_type() {
+        match unpack::<Duration>(&pack(&())) {
+            Err(PayloadError::UnexpectedType { expected, actual }) => {
+                assert_eq!(expected, "type.googleapis.com/google.protobuf.Duration");
+                assert_eq!(actual, "type.googleapis.com/google.protobuf.Empty");
+            },
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn register_a_type_twice() {
+        let mut registry = registry();
+        registry.register(|duration: Duration| Value::Duration(Duration { seconds: -duration.seconds, ..duration }));
+        assert_eq!(registry.duplicates(), &[Duration::type_url()]);
+
+        // The first registration is kept
+        let duration = Duration { seconds: 3, nanos: 5 };
+        assert_eq!(registry.unpack(&pack(&duration)).unwrap(), Value::Duration(duration));
+    }
+
+    fn commands() -> CommandRegistry<Value> {
+        let mut commands = CommandRegistry::new();
+        commands
+            .register("Wait", Value::Duration)
+            .register("Timeout", Value::Duration)
+            .register("Ping", |()| Value::Empty);
+        commands
+    }
+
+    #[test]
+    fn unpack_commands_by_method() {
+        let duration = Duration { seconds: 3, nanos: 5 };
+        assert_eq!(commands().unpack("Wait", &pack(&duration)).unwrap(), Value::Duration(duration.clone()));
+        assert_eq!(commands().unpack("Timeout", &pack(&duration)).unwrap(), Value::Duration(duration));
+        assert_eq!(commands().unpack("Ping", &pack(&())).unwrap(), Value::Empty);
+        assert!(commands().duplicates().is_empty());
+
+        match commands().unpack("Wait", &pack(&())) {
+            Err(PayloadError::UnexpectedType { expected, .. }) => assert_eq!(expected, Duration::type_url()),
+            other => panic!("Unexpected result {:?}", other),
+        }
+        match commands().unpack("Stop", &pack(&())) {
+            Err(PayloadError::UnknownMethod(method)) => assert_eq!(method, "Stop"),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn register_a_method_twice() {
+        let mut commands = commands();
+        commands.register("Ping", |_: Duration| Value::Empty);
+        assert_eq!(commands.duplicates(), &["Ping".to_string()]);
+        assert_eq!(commands.unpack("Ping", &pack(&())).unwrap(), Value::Empty);
+    }
+}
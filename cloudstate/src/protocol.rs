@@ -1,48 +1,112 @@
-extern crate config;
-extern crate log4rs;
-extern crate rustc_version;
-
-use log::{info};
-use actix::prelude::*;
-use crate::protocol::server::GrpcServer;
-use crate::serveless::EntityService;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::health::HealthReporter;
+use crate::listener::Address;
+use crate::metrics::Metrics;
+use crate::shutdown::Shutdown;
+use crate::tls::TlsConfig;
+use crate::serveless::{EntityService, ErrorHandler};
 
 pub mod spec {
     tonic::include_proto!("cloudstate");
-}
 
-#[derive(Debug, Clone)]
-pub struct Options {
-    pub entity_service: EntityService,
-    pub service_name: String,
-    pub service_version: String,
-    pub server_port: u16,
-}
+    pub mod eventsourced {
+        tonic::include_proto!("cloudstate.eventsourced");
+    }
 
-pub struct StartMessage {
-    pub opts: Options,
-}
+    pub mod crdt {
+        tonic::include_proto!("cloudstate.crdt");
+    }
+
+    pub mod function {
+        tonic::include_proto!("cloudstate.function");
+
+        impl FunctionReply {
+
+            pub fn reply(payload: ::prost_types::Any) -> Self {
+                FunctionReply {
+                    response: Some(function_reply::Response::Reply(super::Reply {
+                        payload: Some(payload),
+                    })),
+                    side_effects: Vec::new(),
+                }
+            }
+
+            pub fn forward(service_name: String, command_name: String, payload: ::prost_types::Any) -> Self {
+                FunctionReply {
+                    response: Some(function_reply::Response::Forward(super::Forward {
+                        service_name,
+                        command_name,
+                        payload: Some(payload),
+                    })),
+                    side_effects: Vec::new(),
+                }
+            }
+
+            pub fn side_effect(mut self, service_name: String, command_name: String, payload: ::prost_types::Any, synchronous: bool) -> Self {
+                self.side_effects.push(super::SideEffect {
+                    service_name,
+                    command_name,
+                    payload: Some(payload),
+                    synchronous,
+                });
+                self
+            }
+        }
+    }
+
+    impl Reply {
+
+        /// Packs the reply message, which must be of the output type of the command.
+        pub fn new<M: crate::payload::MessageName>(message: &M) -> Self {
+            Reply {
+                payload: Some(crate::payload::pack(message)),
+            }
+        }
+    }
+
+    impl Failure {
 
-impl Message for StartMessage {
-    type Result = Result<bool, std::io::Error>;
+        pub fn new(command_id: i64, description: impl Into<String>) -> Self {
+            Failure {
+                command_id,
+                description: description.into(),
+            }
+        }
+    }
 }
 
-pub struct ProtocolHandlerActor;
+/// Version of the CloudState protocol implemented by this support library.
+pub const PROTOCOL_MAJOR_VERSION: i32 = 0;
+pub const PROTOCOL_MINOR_VERSION: i32 = 1;
 
-impl Actor for ProtocolHandlerActor {
-    type Context = Context<Self>;
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub entity_services: BTreeMap<String, EntityService>,
+    pub descriptor: Vec<u8>,
+    pub error_handler: Option<ErrorHandler>,
+    pub metrics: Arc<Metrics>,
+    pub shutdown: Arc<Shutdown>,
+    pub shutdown_timeout: Duration,
+    pub metrics_address: Option<SocketAddr>,
+    pub service_name: String,
+    pub service_version: String,
+    pub address: Address,
+    pub tls: Option<TlsConfig>,
+    pub health: HealthReporter,
 }
 
-// Actor handler
-impl Handler<StartMessage> for ProtocolHandlerActor {
-    type Result = Result<bool, std::io::Error>;
+impl Options {
 
-    fn handle(&mut self, _msg: StartMessage, _ctx: &mut Context<Self>) -> Self::Result {
-        info!("Starting server and register messages");
-        Ok(
-            GrpcServer::new(_msg.opts)
-            .start().is_ok()
-        )
+    /// Finds the entity service registered under `service_name`, checking it is of `entity_type`.
+    pub fn entity_service(&self, service_name: &str, entity_type: &str) -> Result<&EntityService, String> {
+        match self.entity_services.get(service_name) {
+            Some(service) if service.entity_type == entity_type => Ok(service),
+            Some(service) => Err(format!("Service {:?} is a {:?} entity, not {:?}", service_name, service.entity_type, entity_type)),
+            None => Err(format!("Unknown service {:?}", service_name)),
+        }
     }
 }
 
@@ -50,20 +114,47 @@ impl Handler<StartMessage> for ProtocolHandlerActor {
 pub mod server {
 
     use tokio::runtime::Runtime;
-    use crate::protocol::Options;
-    use super::rustc_version::version;
+    use crate::protocol::{Options, PROTOCOL_MAJOR_VERSION, PROTOCOL_MINOR_VERSION};
     use log::{info, debug};
-    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-    use std::fs::File;
-    use std::io::Read;
+    use std::sync::Arc;
+
+    use std::future::Future;
+    use futures_util::future::{self, Either};
+    use tonic::{transport::{Server, ServerTlsConfig}, Code, Request, Response, Status, Streaming};
+    use tonic::body::BoxBody;
+    use tonic::codegen::Service;
+    #[cfg(unix)]
+    use tonic::{codegen::{BoxFuture, Never}, transport::{Body, ServiceName}};
+
+    use crate::{crdt, eventsourced, function, shutdown};
+    use crate::shutdown::Shutdown;
+    use crate::serveless::{CRDT, EVENT_SOURCED};
+    use crate::trace::TraceContext;
+    use crate::error::Error;
+    use crate::handlers::handler::ReplySender;
+    use crate::health::HealthService;
+    use crate::health::spec::server::HealthServer;
+    use crate::listener::Address;
+    use crate::reflection::{Index, ReflectionService};
+    use crate::reflection::spec::server::ServerReflectionServer;
 
-    use tonic::{transport::Server, Request, Response, Status};
-    //use prost_types::{FileDescriptorProto, FileDescriptorSet};
 
     use crate::protocol::spec::{
         server::{EntityDiscovery, EntityDiscoveryServer},
         ProxyInfo, EntitySpec, ServiceInfo, Entity,UserFunctionError,
     };
+    use crate::protocol::spec::eventsourced::{
+        server::{EventSourced, EventSourcedServer},
+        EventSourcedStreamIn, EventSourcedStreamOut,
+    };
+    use crate::protocol::spec::crdt::{
+        server::{Crdt, CrdtServer},
+        CrdtStreamIn, CrdtStreamOut,
+    };
+    use crate::protocol::spec::function::{
+        server::{StatelessFunction, StatelessFunctionServer},
+        FunctionCommand, FunctionReply,
+    };
 
     #[derive(Debug, Clone)]
     pub struct Discover {
@@ -85,17 +176,41 @@ pub mod server {
             info!("Received discovery call from sidecar [{:?} {:?}] supporting CloudState {:?}.{:?}", proxy_info.proxy_name, proxy_info.proxy_version, proxy_info.protocol_major_version, proxy_info.protocol_minor_version);
             info!("Supported sidecar entity types: {:?}", proxy_info.supported_entity_types);
 
-            let entity = Entity {
-                entity_type: self.opts.entity_service.entity_type.to_string(),
-                service_name: self.opts.service_name.to_string(),
-                persistence_id: self.opts.entity_service.persistence_id.to_string(),
-            };
+            if proxy_info.protocol_major_version != PROTOCOL_MAJOR_VERSION {
+                error!("Incompatible sidecar protocol version {:?}.{:?}", proxy_info.protocol_major_version, proxy_info.protocol_minor_version);
+                return Err(Error::Protocol(format!(
+                    "CloudState protocol {}.{} is incompatible with version {}.{} implemented by the user function",
+                    proxy_info.protocol_major_version, proxy_info.protocol_minor_version,
+                    PROTOCOL_MAJOR_VERSION, PROTOCOL_MINOR_VERSION,
+                )).into());
+            }
+
+            if proxy_info.protocol_minor_version < PROTOCOL_MINOR_VERSION {
+                warn!("Sidecar supports CloudState protocol {}.{}, older than {}.{}", proxy_info.protocol_major_version, proxy_info.protocol_minor_version, PROTOCOL_MAJOR_VERSION, PROTOCOL_MINOR_VERSION);
+            }
 
-            let vec_entities = vec![entity];
+            for (service_name, entity_service) in &self.opts.entity_services {
+                if !proxy_info.supported_entity_types.contains(&entity_service.entity_type) {
+                    error!("Sidecar does not support entity type {:?} of service {:?}", entity_service.entity_type, service_name);
+                    return Err(Error::Protocol(format!(
+                        "Entity type {} of service {} is not supported by the sidecar",
+                        entity_service.entity_type, service_name,
+                    )).into());
+                }
+            }
+
+            let vec_entities: Vec<Entity> = self.opts.entity_services.iter()
+                .map(|(service_name, entity_service)| Entity {
+                    entity_type: entity_service.entity_type.to_string(),
+                    service_name: service_name.to_string(),
+                    persistence_id: entity_service.persistence_id.to_string(),
+                })
+                .collect();
 
             let lib_name: String = String::from("cloudstate-rust-support");
             let lib_version: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
-            let runtime = "rustc ".to_owned() + &version().unwrap().to_string();
+            // Version of the compiler that built the user function, see build.rs
+            let runtime = format!("rustc {}", env!("CLOUDSTATE_RUSTC_VERSION"));
 
             let info = ServiceInfo {
                 service_name: self.opts.service_name.to_string(),
@@ -105,18 +220,7 @@ pub mod server {
                 support_library_version: lib_version.unwrap_or("0.5.0").to_string(),
             };
 
-            // protoc --include_imports \
-            // --proto_path=<proto file directory> \
-            // --descriptor_set_out=user-function.desc \
-            // <path to .proto files>
-            //let tmp = tempfile::Builder::new().prefix("prost-build").tempdir()?;
-            //let descriptor_set = tmp.path().join("prost-descriptor-set");
-
-
-            let mut file = File::open("user-function.desc").unwrap();
-
-            let mut data = Vec::new();
-            file.read_to_end(&mut data).unwrap();
+            let data = self.opts.descriptor.clone();
 
             let reply = EntitySpec {
                 proto: data,
@@ -124,6 +228,8 @@ pub mod server {
                 service_info: Some(info),
             };
 
+            // The proxy only starts forwarding commands once it discovered the entities
+            self.opts.health.set_ready(true);
             Ok(Response::new(reply))
         }
 
@@ -138,11 +244,302 @@ pub mod server {
 
             debug!("Receive request for report_error. Metadata: {:?}", metadata );
             error!("Received report_error from sidecar. Error: {:?}", user_func_error.message);
+
+            self.opts.metrics.error_reported();
+            if let Some(handler) = self.opts.error_handler.as_ref() {
+                handler.handle(user_func_error);
+            }
+
             Ok(Response::new(()))
         }
 
     }
 
+    #[derive(Debug, Clone)]
+    pub struct EventSourcedService {
+        pub opts: Options,
+    }
+
+    #[tonic::async_trait]
+    impl EventSourced for EventSourcedService {
+
+        // Named after the rpc of the protocol, as generated
+        type handleStream = shutdown::Replies<EventSourcedStreamOut>;
+
+        async fn handle(
+            &self,
+            request: Request<Streaming<EventSourcedStreamIn>>,
+        ) -> Result<Response<Self::handleStream>, Status> {
+
+            self.opts.shutdown.accept()?;
+            let trace = TraceContext::from_headers(request.metadata());
+            let mut stream = request.into_inner();
+            let shutdown = self.opts.shutdown.clone();
+            let mut entity = eventsourced::EntityStream::new(self.opts.clone()).with_trace(trace);
+            let metrics = self.opts.metrics.clone();
+            let (mut tx, rx) = shutdown::replies(&self.opts.shutdown, 4);
+
+            tokio::spawn(async move {
+                let _stream = metrics.stream_opened(EVENT_SOURCED);
+                loop {
+                    let msg = match stream.message().await {
+                        Ok(Some(msg)) => msg,
+                        Ok(None) => break,
+                        Err(status) => {
+                            error!("Error receiving message from sidecar: {:?}", status);
+                            break;
+                        }
+                    };
+
+                    // Closes the stream instead of handling new commands once draining, the proxy
+                    // reconnects to another instance
+                    if let Err(status) = shutdown.accept() {
+                        tx.send(Err(status)).await.ok();
+                        break;
+                    }
+
+                    // Keeps the server from stopping while the message is handled
+                    let _in_flight = shutdown.begin();
+                    let out = match entity.handle_message(msg) {
+                        Ok(Some(out)) => out,
+                        Ok(None) => continue,
+                        Err(err) => {
+                            error!("Closing entity stream. Failure: {:?}", err.description);
+                            tx.send(Ok(eventsourced::failure_message(err))).await.ok();
+                            break;
+                        }
+                    };
+
+                    if tx.send(Ok(out)).await.is_err() {
+                        debug!("Sidecar closed the entity stream");
+                        break;
+                    }
+                }
+            });
+
+            Ok(Response::new(rx))
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct CrdtService {
+        pub opts: Options,
+    }
+
+    #[tonic::async_trait]
+    impl Crdt for CrdtService {
+
+        type handleStream = shutdown::Replies<CrdtStreamOut>;
+
+        async fn handle(
+            &self,
+            request: Request<Streaming<CrdtStreamIn>>,
+        ) -> Result<Response<Self::handleStream>, Status> {
+
+            self.opts.shutdown.accept()?;
+            let trace = TraceContext::from_headers(request.metadata());
+            let mut stream = request.into_inner();
+            let shutdown = self.opts.shutdown.clone();
+            let mut entity = crdt::CrdtStream::new(self.opts.clone()).with_trace(trace);
+            let metrics = self.opts.metrics.clone();
+            let (mut tx, rx) = shutdown::replies(&self.opts.shutdown, 4);
+
+            tokio::spawn(async move {
+                let _stream = metrics.stream_opened(CRDT);
+                'stream: loop {
+                    let msg = match stream.message().await {
+                        Ok(Some(msg)) => msg,
+                        Ok(None) => break,
+                        Err(status) => {
+                            error!("Error receiving message from sidecar: {:?}", status);
+                            break;
+                        }
+                    };
+
+                    // Closes the stream instead of handling new commands once draining, the proxy
+                    // reconnects to another instance
+                    if let Err(status) = shutdown.accept() {
+                        tx.send(Err(status)).await.ok();
+                        break;
+                    }
+
+                    // Keeps the server from stopping while the message is handled
+                    let _in_flight = shutdown.begin();
+                    let out = match entity.handle_message(msg) {
+                        Ok(out) => out,
+                        Err(err) => {
+                            error!("Closing CRDT stream. Failure: {:?}", err.description);
+                            tx.send(Ok(crdt::failure_message(err))).await.ok();
+                            break;
+                        }
+                    };
+
+                    for msg in out {
+                        if tx.send(Ok(msg)).await.is_err() {
+                            debug!("Sidecar closed the CRDT stream");
+                            break 'stream;
+                        }
+                    }
+                }
+            });
+
+            Ok(Response::new(rx))
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct StatelessFunctionService {
+        pub opts: Options,
+    }
+
+    #[tonic::async_trait]
+    impl StatelessFunction for StatelessFunctionService {
+
+        async fn handle_unary(
+            &self,
+            request: Request<FunctionCommand>,
+        ) -> Result<Response<FunctionReply>, Status> {
+
+            // In flight until the reply is written, see track_single_reply
+            self.opts.shutdown.accept()?;
+            let trace = TraceContext::from_headers(request.metadata());
+            let command = request.into_inner();
+            let timer = function::CommandTimer::start(&self.opts, &command);
+            let span = function::command_span(&command, trace);
+            let result = span.in_scope(|| {
+                debug!("Received unary command {:?} for service {:?}", command.name, command.service_name);
+
+                let handler = function::route(&self.opts, &command)?;
+                handler.handle_unary(command)
+                    .map(Response::new)
+                    .map_err(function::handler_error)
+            });
+            timer.finish(result)
+        }
+
+        async fn handle_streamed_in(
+            &self,
+            request: Request<Streaming<FunctionCommand>>,
+        ) -> Result<Response<FunctionReply>, Status> {
+
+            // In flight until the reply is written, see track_single_reply
+            self.opts.shutdown.accept()?;
+            let trace = TraceContext::from_headers(request.metadata());
+            let mut stream = request.into_inner();
+            let first = match stream.message().await? {
+                Some(command) => command,
+                None => return Err(Status::new(Code::InvalidArgument, "Received an empty command stream")),
+            };
+
+            let timer = function::CommandTimer::start(&self.opts, &first);
+            let handler = match function::route(&self.opts, &first) {
+                Ok(handler) => handler,
+                Err(status) => return timer.finish(Err(status)),
+            };
+            let span = function::command_span(&first, trace);
+            span.in_scope(|| debug!("Received streamed in function command"));
+            let commands = function::command_stream(first, stream);
+
+            let result = function::in_span(span, handler.handle_streamed_in(commands)).await
+                .map(Response::new)
+                .map_err(function::handler_error);
+            timer.finish(result)
+        }
+
+        type handleStreamedOutStream = shutdown::Replies<FunctionReply>;
+
+        async fn handle_streamed_out(
+            &self,
+            request: Request<FunctionCommand>,
+        ) -> Result<Response<Self::handleStreamedOutStream>, Status> {
+
+            self.opts.shutdown.accept()?;
+            let trace = TraceContext::from_headers(request.metadata());
+            let in_flight = self.opts.shutdown.begin();
+            let command = request.into_inner();
+            let timer = function::CommandTimer::start(&self.opts, &command);
+            let handler = match function::route(&self.opts, &command) {
+                Ok(handler) => handler,
+                Err(status) => return timer.finish(Err(status)),
+            };
+            let span = function::command_span(&command, trace);
+            span.in_scope(|| debug!("Received streamed out function command"));
+
+            let (mut tx, rx) = shutdown::replies(&self.opts.shutdown, 4);
+            let replies = ReplySender::new(tx.clone());
+            tokio::spawn(async move {
+                let _in_flight = in_flight;
+                let handled = function::in_span(span, handler.handle_streamed_out(command, replies)).await;
+                if let Err(err) = timer.finish(handled) {
+                    if tx.send(Err(function::handler_error(err))).await.is_err() {
+                        debug!("Sidecar closed the function stream");
+                    }
+                }
+            });
+
+            Ok(Response::new(rx))
+        }
+
+        type handleStreamedStream = shutdown::Replies<FunctionReply>;
+
+        async fn handle_streamed(
+            &self,
+            request: Request<Streaming<FunctionCommand>>,
+        ) -> Result<Response<Self::handleStreamedStream>, Status> {
+
+            self.opts.shutdown.accept()?;
+            let trace = TraceContext::from_headers(request.metadata());
+            let mut stream = request.into_inner();
+            let opts = self.opts.clone();
+            let (mut tx, rx) = shutdown::replies(&self.opts.shutdown, 4);
+
+            tokio::spawn(async move {
+                'stream: loop {
+                    let command = match stream.message().await {
+                        Ok(Some(command)) => command,
+                        Ok(None) => break,
+                        Err(status) => {
+                            error!("Error receiving message from sidecar: {:?}", status);
+                            break;
+                        }
+                    };
+
+                    // No new command once draining, as on the entity streams
+                    if let Err(status) = opts.shutdown.accept() {
+                        tx.send(Err(status)).await.ok();
+                        break;
+                    }
+
+                    let _in_flight = opts.shutdown.begin();
+                    let timer = function::CommandTimer::start(&opts, &command);
+                    let span = function::command_span(&command, trace);
+                    let replies = timer.finish(span.in_scope(|| {
+                        function::route(&opts, &command)
+                            .and_then(|handler| handler.handle_streamed(command).map_err(function::handler_error))
+                    }));
+
+                    match replies {
+                        Ok(replies) => {
+                            for reply in replies {
+                                if tx.send(Ok(reply)).await.is_err() {
+                                    debug!("Sidecar closed the function stream");
+                                    break 'stream;
+                                }
+                            }
+                        },
+                        Err(status) => {
+                            error!("Closing function stream. Error: {:?}", status.message());
+                            tx.send(Err(status)).await.ok();
+                            break;
+                        }
+                    }
+                }
+            });
+
+            Ok(Response::new(rx))
+        }
+    }
+
     pub struct GrpcServer {
         pub options: Options,
     }
@@ -155,37 +552,285 @@ pub mod server {
             }
         }
 
-        pub fn start(self) -> Result<(), Box<dyn std::error::Error>> {
+        /// Blocks the current thread serving on a new tokio runtime, until SIGINT or SIGTERM.
+        pub fn start(self) -> Result<(), Error> {
             // Create the runtime
-            let rt = Runtime::new().unwrap();
+            let rt = Runtime::new()?;
 
-            let clone_opts = self.options;
+            rt.block_on(self.serve_with_shutdown(shutdown::signal()))
+                .map_err(|err| {
+                    error!("Error during start server phase: {}", err);
+                    err
+                })
+        }
 
-            rt.block_on(async {
-                debug!("Now running on a worker thread");
+        /// Serves all the CloudState services on the runtime of the caller until the server fails.
+        /// With the `prometheus` feature the metrics endpoint is served alongside, if configured.
+        pub async fn serve(self) -> Result<(), Error> {
+            #[cfg(feature = "prometheus")]
+            {
+                if let Some(addr) = self.options.metrics_address {
+                    let metrics = crate::metrics::serve(addr, self.options.metrics.clone());
+                    return match future::select(Box::pin(serve_grpc(self.options)), Box::pin(metrics)).await {
+                        Either::Left((result, _)) | Either::Right((result, _)) => result,
+                    };
+                }
+            }
+            serve_grpc(self.options).await
+        }
 
-                let opts = clone_opts.clone();
-                let discover = Discover{ opts: clone_opts };
+        /// Serves until `signal` completes. New streams and commands are then rejected while the commands
+        /// in flight get up to `Options::shutdown_timeout` to complete and send their replies.
+        pub async fn serve_with_shutdown<F>(self, signal: F) -> Result<(), Error>
+            where F: Future<Output = ()> {
+            let shutdown = self.options.shutdown.clone();
+            let health = self.options.health.clone();
+            let timeout = self.options.shutdown_timeout;
+
+            let mut server = Box::pin(self.serve());
+            if let Either::Left((result, _)) = future::select(server.as_mut(), Box::pin(signal)).await {
+                return result;
+            }
 
-                let addr = SocketAddr::new(
-                    IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
-                    opts.server_port
-                );
+            info!("Shutdown signal received. Stopping CloudState gRPC server");
+            health.set_ready(false);
+            // The server keeps running while draining so that the replies can be written
+            let drained = future::select(server, Box::pin(shutdown::drain(&shutdown, timeout))).await;
+            match drained {
+                Either::Left((result, _)) => result,
+                Either::Right(_) => Ok(()),
+            }
+        }
+    }
 
-                info!("Start CloudState gRPC in 0.0.0.0:{}", opts.server_port);
-                Server::builder()
-                    .add_service(EntityDiscoveryServer::new(discover))
-                    .serve(addr)
-                    .await
-                    .map_err(|err| error!("Error during start server phase: {:?}", err))
-                    .ok();
-            });
 
-            Ok(())
+    /// Serves the gRPC services on the configured address until the server fails.
+    async fn serve_grpc(opts: Options) -> Result<(), Error> {
+        let index = Index::new(&opts.descriptor)
+            .map_err(|err| Error::Descriptor(format!("Unable to decode the descriptor set: {}", err)))?;
+        let routes = Routes {
+            discover: EntityDiscoveryServer::new(Discover{ opts: opts.clone() }),
+            event_sourced: EventSourcedServer::new(EventSourcedService{ opts: opts.clone() }),
+            crdt: CrdtServer::new(CrdtService{ opts: opts.clone() }),
+            stateless_function: StatelessFunctionServer::new(StatelessFunctionService{ opts: opts.clone() }),
+            health: HealthServer::new(HealthService{ reporter: opts.health.clone() }),
+            reflection: ServerReflectionServer::new(ReflectionService{ index: Arc::new(index) }),
+        };
+
+        info!("Start CloudState gRPC in {}", opts.address);
+        match opts.address {
+            Address::Tcp(addr) => {
+                let mut builder = Server::builder();
+                let shutdown = opts.shutdown.clone();
+                builder.interceptor_fn(move |service, request| {
+                    let path = request.uri().path().to_string();
+                    track_single_reply(&shutdown, &path, service.call(request))
+                });
+                let mut watch = None;
+                if let Some(tls) = opts.tls {
+                    let (config, files) = tls.server_config().map_err(Error::Config)?;
+                    builder.tls_config(ServerTlsConfig::with_rustls().rustls_server_config(config));
+                    watch = Some(tls.watch(files));
+                }
+
+                let server = builder
+                    .add_service(routes.discover)
+                    .add_service(routes.event_sourced)
+                    .add_service(routes.crdt)
+                    .add_service(routes.stateless_function)
+                    .add_service(routes.health)
+                    .add_service(routes.reflection)
+                    .serve(addr);
+
+                match watch {
+                    // Reloads the certificate for as long as the server runs
+                    Some(watch) => {
+                        if let Either::Left((result, _)) = future::select(Box::pin(server), Box::pin(watch)).await {
+                            result?;
+                        }
+                    },
+                    None => server.await?,
+                }
+                Ok(())
+            },
+            #[cfg(unix)]
+            Address::Unix(path) => serve_unix(path, routes, opts.shutdown).await,
+        }
+    }
+
+    /// Calls answered with a single reply, once the handler returns the reply still has to be written.
+    const SINGLE_REPLY_CALLS: [&str; 2] = [
+        "/cloudstate.function.StatelessFunction/handleUnary",
+        "/cloudstate.function.StatelessFunction/handleStreamedIn",
+    ];
+
+    /// Keeps a call answered with a single reply in flight until its response has been written,
+    /// the replies of the streamed calls are tracked by `shutdown::Replies`.
+    fn track_single_reply<F, E>(shutdown: &Arc<Shutdown>, path: &str, response: F) -> impl Future<Output = Result<http::Response<BoxBody>, E>>
+        where F: Future<Output = Result<http::Response<BoxBody>, E>> {
+        let in_flight = if SINGLE_REPLY_CALLS.contains(&path) {
+            Some(shutdown.begin())
+        } else {
+            None
+        };
+
+        async move {
+            let response = response.await?;
+            Ok(match in_flight {
+                Some(in_flight) => shutdown::track(response, in_flight),
+                None => response,
+            })
+        }
+    }
+
+    /// The CloudState services, routed by the gRPC service name prefixing the request path.
+    #[derive(Clone)]
+    struct Routes {
+        discover: EntityDiscoveryServer<Discover>,
+        event_sourced: EventSourcedServer<EventSourcedService>,
+        crdt: CrdtServer<CrdtService>,
+        stateless_function: StatelessFunctionServer<StatelessFunctionService>,
+        health: HealthServer<HealthService>,
+        reflection: ServerReflectionServer<ReflectionService>,
+    }
+
+    // Tonic routes the calls over TCP, the unix domain socket is served by hyper alone
+    #[cfg(unix)]
+    impl Routes {
+
+        fn call(&mut self, request: http::Request<Body>) -> BoxFuture<http::Response<BoxBody>, Never> {
+            let path = request.uri().path().to_string();
+            if routes_to::<EntityDiscoveryServer<Discover>>(&path) {
+                Box::pin(self.discover.call(request))
+            } else if routes_to::<EventSourcedServer<EventSourcedService>>(&path) {
+                Box::pin(self.event_sourced.call(request))
+            } else if routes_to::<CrdtServer<CrdtService>>(&path) {
+                Box::pin(self.crdt.call(request))
+            } else if routes_to::<StatelessFunctionServer<StatelessFunctionService>>(&path) {
+                Box::pin(self.stateless_function.call(request))
+            } else if routes_to::<HealthServer<HealthService>>(&path) {
+                Box::pin(self.health.call(request))
+            } else if routes_to::<ServerReflectionServer<ReflectionService>>(&path) {
+                Box::pin(self.reflection.call(request))
+            } else {
+                debug!("No service for request path {:?}", path);
+                let response = http::Response::builder()
+                    .status(200)
+                    .header("grpc-status", "12")
+                    .body(BoxBody::empty())
+                    .unwrap();
+                Box::pin(future::ok(response))
+            }
         }
     }
 
+    #[cfg(unix)]
+    fn routes_to<S: ServiceName>(path: &str) -> bool {
+        path.starts_with(&format!("/{}/", S::NAME))
+    }
+
+    /// Serves the routes over HTTP/2 on a unix domain socket, replacing a stale socket file.
+    #[cfg(unix)]
+    async fn serve_unix(path: std::path::PathBuf, routes: Routes, shutdown: Arc<Shutdown>) -> Result<(), Error> {
+        use hyper::server::accept::from_stream;
+        use hyper::service::{make_service_fn, service_fn};
+        use tokio::net::UnixListener;
+        use crate::listener;
+
+        listener::remove_stale_socket(&path)?;
+        let incoming = UnixListener::bind(&path)?.incoming();
+
+        let make_service = make_service_fn(move |_| {
+            let mut routes = routes.clone();
+            let shutdown = shutdown.clone();
+            future::ok::<_, Never>(service_fn(move |request: http::Request<Body>| {
+                let path = request.uri().path().to_string();
+                track_single_reply(&shutdown, &path, routes.call(request))
+            }))
+        });
+
+        hyper::Server::builder(from_stream(incoming))
+            .http2_only(true)
+            .serve(make_service)
+            .await?;
+        Ok(())
+    }
+
 }
 
+#[cfg(test)]
+mod tests {
+    use tokio::runtime::Runtime;
+    use tonic::{Code, Request, Response, Status};
+    use futures_util::future;
+    use crate::fixture::{self, SERVICE_NAME};
+    use crate::health::ServingStatus;
+    use crate::protocol::{PROTOCOL_MAJOR_VERSION, PROTOCOL_MINOR_VERSION};
+    use crate::protocol::server::{Discover, GrpcServer};
+    use crate::protocol::spec::{EntitySpec, ProxyInfo};
+    use crate::protocol::spec::server::EntityDiscovery;
+    use crate::serveless::{EntityService, CRDT, EVENT_SOURCED};
+
+    fn discover(major_version: i32, entity_types: &[&str]) -> Result<Response<EntitySpec>, Status> {
+        let service = EntityService::new()
+            .persistence_id("shopping-cart".to_string())
+            .event_sourced();
+        let discover = Discover { opts: fixture::cloudstate(service).entity_options().unwrap() };
+        let proxy_info = ProxyInfo {
+            protocol_major_version: major_version,
+            protocol_minor_version: PROTOCOL_MINOR_VERSION,
+            proxy_name: "test-proxy".to_string(),
+            proxy_version: "0.0.0".to_string(),
+            supported_entity_types: entity_types.iter().map(|entity_type| entity_type.to_string()).collect(),
+        };
+        Runtime::new().unwrap().block_on(discover.discover(Request::new(proxy_info)))
+    }
+
+    #[test]
+    fn discover_lists_the_entities() {
+        let spec = discover(PROTOCOL_MAJOR_VERSION, &[EVENT_SOURCED, CRDT]).unwrap().into_inner();
+        assert_eq!(spec.entities.len(), 1);
+        assert_eq!(spec.entities[0].service_name, SERVICE_NAME);
+        assert_eq!(spec.entities[0].entity_type, EVENT_SOURCED);
+        assert_eq!(spec.entities[0].persistence_id, "shopping-cart");
+        assert!(!spec.proto.is_empty());
+    }
+
+    #[test]
+    fn discover_rejects_another_major_version() {
+        let status = discover(PROTOCOL_MAJOR_VERSION + 1, &[EVENT_SOURCED]).unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert!(status.message().contains("incompatible"), "{}", status.message());
+    }
 
+    #[test]
+    fn discover_rejects_an_unsupported_entity_type() {
+        let status = discover(PROTOCOL_MAJOR_VERSION, &[CRDT]).unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert!(status.message().contains("not supported by the sidecar"), "{}", status.message());
+    }
 
+    #[test]
+    fn health_follows_discovery_and_shutdown() {
+        let service = EntityService::new().event_sourced();
+        let mut cloudstate = fixture::cloudstate(service);
+        cloudstate.bind("127.0.0.1:0".parse().unwrap());
+        let opts = cloudstate.options().unwrap();
+        let health = opts.health.clone();
+        assert_eq!(health.status(SERVICE_NAME), Some(ServingStatus::NotServing));
+
+        let discover = Discover { opts: opts.clone() };
+        let proxy_info = ProxyInfo {
+            protocol_major_version: PROTOCOL_MAJOR_VERSION,
+            protocol_minor_version: PROTOCOL_MINOR_VERSION,
+            supported_entity_types: vec![EVENT_SOURCED.to_string()],
+            ..Default::default()
+        };
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(discover.discover(Request::new(proxy_info))).unwrap();
+        assert_eq!(health.status(SERVICE_NAME), Some(ServingStatus::Serving));
+
+        runtime.block_on(GrpcServer::new(opts).serve_with_shutdown(future::ready(()))).unwrap();
+        assert_eq!(health.status(SERVICE_NAME), Some(ServingStatus::NotServing));
+    }
+}
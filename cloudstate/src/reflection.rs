@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use prost::{DecodeError, Message};
+use prost_types::{DescriptorProto, FileDescriptorProto, FileDescriptorSet};
+use tokio::sync::mpsc;
+use tonic::{Code, Request, Response, Status, Streaming};
+use crate::payload;
+
+pub mod spec {
+    tonic::include_proto!("grpc.reflection.v1alpha");
+}
+
+use self::spec::{
+    server_reflection_request, server_reflection_response,
+    ErrorResponse, ExtensionNumberResponse, ExtensionRequest, FileDescriptorResponse,
+    ListServiceResponse, ServerReflectionRequest, ServerReflectionResponse, ServiceResponse,
+};
+use self::spec::server::ServerReflection;
+
+/// The files, symbols and services of the descriptor set sent to the proxy on discovery.
+#[derive(Debug, Default)]
+pub struct Index {
+    files: HashMap<String, FileDescriptorProto>,
+    /// File declaring each fully qualified message, enum, service, method and extension.
+    symbols: HashMap<String, String>,
+    /// Extension numbers and declaring file of each extended message.
+    extensions: HashMap<String, Vec<(i32, String)>>,
+    services: Vec<String>,
+}
+
+impl Index {
+
+    pub fn new(descriptor_set: &[u8]) -> Result<Self, DecodeError> {
+        let descriptors = FileDescriptorSet::decode(descriptor_set)?;
+
+        let mut index = Index::default();
+        for file in descriptors.file {
+            index.add_file(file);
+        }
+        Ok(index)
+    }
+
+    fn add_file(&mut self, file: FileDescriptorProto) {
+        let file_name = file.name.clone().unwrap_or_default();
+        let package = file.package.clone().unwrap_or_default();
+
+        for message in &file.message_type {
+            self.add_message(&file_name, &package, message);
+        }
+        for enum_type in &file.enum_type {
+            self.add_symbol(&file_name, &package, enum_type.name.as_ref());
+        }
+        for extension in &file.extension {
+            self.add_symbol(&file_name, &package, extension.name.as_ref());
+            self.add_extension(&file_name, extension.extendee.as_ref(), extension.number);
+        }
+        for service in &file.service {
+            let service_name = qualified_name(&package, service.name.as_ref());
+            for method in &service.method {
+                self.add_symbol(&file_name, &service_name, method.name.as_ref());
+            }
+            self.symbols.insert(service_name.clone(), file_name.clone());
+            self.services.push(service_name);
+        }
+
+        self.files.insert(file_name, file);
+    }
+
+    fn add_message(&mut self, file_name: &str, scope: &str, message: &DescriptorProto) {
+        let name = qualified_name(scope, message.name.as_ref());
+        for nested in &message.nested_type {
+            self.add_message(file_name, &name, nested);
+        }
+        for enum_type in &message.enum_type {
+            self.add_symbol(file_name, &name, enum_type.name.as_ref());
+        }
+        for extension in &message.extension {
+            self.add_symbol(file_name, &name, extension.name.as_ref());
+            self.add_extension(file_name, extension.extendee.as_ref(), extension.number);
+        }
+        self.symbols.insert(name, file_name.to_string());
+    }
+
+    fn add_symbol(&mut self, file_name: &str, scope: &str, name: Option<&String>) {
+        self.symbols.insert(qualified_name(scope, name), file_name.to_string());
+    }
+
+    fn add_extension(&mut self, file_name: &str, extendee: Option<&String>, number: Option<i32>) {
+        if let (Some(extendee), Some(number)) = (extendee, number) {
+            self.extensions.entry(extendee.trim_start_matches('.').to_string())
+                .or_insert_with(Vec::new)
+                .push((number, file_name.to_string()));
+        }
+    }
+
+    /// The file with all its transitive dependencies, serialized, the requested file first.
+    fn file_with_dependencies(&self, file_name: &str) -> Option<Vec<Vec<u8>>> {
+        self.files.get(file_name)?;
+
+        let mut encoded = Vec::new();
+        let mut seen = HashSet::new();
+        let mut pending = vec![file_name.to_string()];
+        while let Some(name) = pending.pop() {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            if let Some(file) = self.files.get(&name) {
+                encoded.push(payload::encode(file));
+                pending.extend(file.dependency.iter().cloned());
+            }
+        }
+        Some(encoded)
+    }
+
+    fn handle(&self, request: &ServerReflectionRequest) -> server_reflection_response::MessageResponse {
+        use self::server_reflection_request::MessageRequest;
+
+        let files = match request.message_request.as_ref() {
+            Some(MessageRequest::FileByFilename(file_name)) => self.file_with_dependencies(file_name)
+                .ok_or_else(|| format!("Unknown file {:?}", file_name)),
+            Some(MessageRequest::FileContainingSymbol(symbol)) => self.symbols.get(symbol)
+                .and_then(|file_name| self.file_with_dependencies(file_name))
+                .ok_or_else(|| format!("Unknown symbol {:?}", symbol)),
+            Some(MessageRequest::FileContainingExtension(ExtensionRequest { containing_type, extension_number })) =>
+                self.extensions.get(containing_type)
+                    .and_then(|extensions| extensions.iter().find(|(number, _)| number == extension_number))
+                    .and_then(|(_, file_name)| self.file_with_dependencies(file_name))
+                    .ok_or_else(|| format!("Unknown extension {:?} of {:?}", extension_number, containing_type)),
+            Some(MessageRequest::AllExtensionNumbersOfType(type_name)) => {
+                if !self.symbols.contains_key(type_name) {
+                    return error_response(Code::NotFound, format!("Unknown type {:?}", type_name));
+                }
+                let extension_number = self.extensions.get(type_name)
+                    .map(|extensions| extensions.iter().map(|(number, _)| *number).collect())
+                    .unwrap_or_default();
+                return server_reflection_response::MessageResponse::AllExtensionNumbersResponse(ExtensionNumberResponse {
+                    base_type_name: type_name.clone(),
+                    extension_number,
+                });
+            },
+            Some(MessageRequest::ListServices(_)) => {
+                return server_reflection_response::MessageResponse::ListServicesResponse(ListServiceResponse {
+                    service: self.services.iter()
+                        .map(|name| ServiceResponse { name: name.clone() })
+                        .collect(),
+                });
+            },
+            None => return error_response(Code::InvalidArgument, String::from("Empty reflection request")),
+        };
+
+        match files {
+            Ok(file_descriptor_proto) => server_reflection_response::MessageResponse::FileDescriptorResponse(
+                FileDescriptorResponse { file_descriptor_proto },
+            ),
+            Err(message) => error_response(Code::NotFound, message),
+        }
+    }
+}
+
+fn qualified_name(scope: &str, name: Option<&String>) -> String {
+    let name = name.map(String::as_str).unwrap_or_default();
+    if scope.is_empty() { name.to_string() } else { format!("{}.{}", scope, name) }
+}
+
+fn error_response(code: Code, error_message: String) -> server_reflection_response::MessageResponse {
+    server_reflection_response::MessageResponse::ErrorResponse(ErrorResponse {
+        error_code: code as i32,
+        error_message,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct ReflectionService {
+    pub index: Arc<Index>,
+}
+
+#[tonic::async_trait]
+impl ServerReflection for ReflectionService {
+
+    type ServerReflectionInfoStream = mpsc::Receiver<Result<ServerReflectionResponse, Status>>;
+
+    async fn server_reflection_info(
+        &self,
+        request: Request<Streaming<ServerReflectionRequest>>,
+    ) -> Result<Response<Self::ServerReflectionInfoStream>, Status> {
+
+        let mut stream = request.into_inner();
+        let index = self.index.clone();
+        let (mut tx, rx) = mpsc::channel(4);
+
+        tokio::spawn(async move {
+            loop {
+                let request = match stream.message().await {
+                    Ok(Some(request)) => request,
+                    Ok(None) => break,
+                    Err(status) => {
+                        debug!("Error receiving reflection request: {:?}", status);
+                        break;
+                    }
+                };
+
+                let response = ServerReflectionResponse {
+                    valid_host: request.host.clone(),
+                    message_response: Some(index.handle(&request)),
+                    original_request: Some(request),
+                };
+                if tx.send(Ok(response)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use prost_types::FileDescriptorProto;
+    use tonic::Code;
+    use crate::fixture;
+    use super::Index;
+    use super::spec::{server_reflection_request::MessageRequest, server_reflection_response::MessageResponse};
+    use super::spec::{ExtensionRequest, ServerReflectionRequest};
+
+    fn index() -> Index {
+        Index::new(fixture::DESCRIPTOR).unwrap()
+    }
+
+    fn handle(request: MessageRequest) -> MessageResponse {
+        index().handle(&ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(request),
+        })
+    }
+
+    // Names of the returned files, the requested one first
+    fn file_names(response: MessageResponse) -> Vec<String> {
+        match response {
+            MessageResponse::FileDescriptorResponse(response) => response.file_descriptor_proto.iter()
+                .map(|bytes| FileDescriptorProto::decode(&bytes[..]).unwrap().name.unwrap_or_default())
+                .collect(),
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+
+    fn error_code(response: MessageResponse) -> i32 {
+        match response {
+            MessageResponse::ErrorResponse(error) => error.error_code,
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+
+    #[test]
+    fn list_services() {
+        match handle(MessageRequest::ListServices(String::new())) {
+            MessageResponse::ListServicesResponse(response) => {
+                let names: Vec<&str> = response.service.iter().map(|service| service.name.as_str()).collect();
+                assert_eq!(names, vec![fixture::SERVICE_NAME]);
+            },
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+
+    #[test]
+    fn file_containing_symbol_with_its_dependencies() {
+        for symbol in &["com.example.shoppingcart.AddLineItem", "com.example.shoppingcart.ShoppingCart.AddItem"] {
+            let names = file_names(handle(MessageRequest::FileContainingSymbol(symbol.to_string())));
+            assert_eq!(names[0], "example/shoppingcart/shoppingcart.proto");
+            for dependency in &["cloudstate/entity_key.proto", "google/api/http.proto", "google/proto/descriptor.proto"] {
+                assert!(names.iter().any(|name| name == dependency), "{} not in {:?}", dependency, names);
+            }
+            assert!(!names.iter().any(|name| name == "example/shoppingcart/persistence/domain.proto"));
+        }
+    }
+
+    #[test]
+    fn file_by_filename() {
+        let names = file_names(handle(MessageRequest::FileByFilename(String::from("example/shoppingcart/persistence/domain.proto"))));
+        assert_eq!(names, vec!["example/shoppingcart/persistence/domain.proto"]);
+    }
+
+    #[test]
+    fn extensions() {
+        let request = ExtensionRequest {
+            containing_type: String::from("google.protobuf.FieldOptions"),
+            extension_number: 50002,
+        };
+        let names = file_names(handle(MessageRequest::FileContainingExtension(request)));
+        assert_eq!(names[0], "cloudstate/entity_key.proto");
+
+        match handle(MessageRequest::AllExtensionNumbersOfType(String::from("google.protobuf.FieldOptions"))) {
+            MessageResponse::AllExtensionNumbersResponse(response) => assert!(response.extension_number.contains(&50002)),
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+
+    #[test]
+    fn unknown_files_and_symbols() {
+        let not_found = Code::NotFound as i32;
+        assert_eq!(error_code(handle(MessageRequest::FileByFilename(String::from("unknown.proto")))), not_found);
+        assert_eq!(error_code(handle(MessageRequest::FileContainingSymbol(String::from("com.example.Unknown")))), not_found);
+        assert_eq!(error_code(handle(MessageRequest::AllExtensionNumbersOfType(String::from("com.example.Unknown")))), not_found);
+
+        let request = ExtensionRequest {
+            containing_type: String::from("google.protobuf.FieldOptions"),
+            extension_number: 1,
+        };
+        assert_eq!(error_code(handle(MessageRequest::FileContainingExtension(request))), not_found);
+    }
+}
@@ -1,15 +1,104 @@
-extern crate config;
-extern crate log4rs;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use crate::descriptor::{self, Method};
+use crate::eventsourced::{EntityInstance, EntityTypes};
+use crate::error::Error;
+use crate::health::HealthReporter;
+use crate::listener;
+use crate::metrics::Metrics;
+use crate::payload;
+use crate::handlers::handler::{CrdtHandler, EventSourcedEntity, EventSourcedHandler, FunctionHandler};
+use crate::protocol::Options;
+use crate::protocol::server::GrpcServer;
+use crate::settings::{EntitySettings, Settings};
+use crate::shutdown::{self, Shutdown};
+use crate::tls::TlsConfig;
+use crate::protocol::spec::UserFunctionError;
 
-use log::info;
-use actix::prelude::*;
-use crate::protocol::{Options, ProtocolHandlerActor, StartMessage};
+pub const EVENT_SOURCED: &str = "cloudstate.eventsourced.EventSourced";
+pub const CRDT: &str = "cloudstate.crdt.Crdt";
+pub const STATELESS_FUNCTION: &str = "cloudstate.function.StatelessFunction";
+
+/// Creates the handler of an entity given its entity id.
+pub struct HandlerFactory<H: ?Sized>(Arc<dyn Fn(&str) -> Box<H> + Send + Sync>);
+
+impl<H: ?Sized> HandlerFactory<H> {
+
+    pub fn create(&self, entity_id: &str) -> Box<H> {
+        (self.0)(entity_id)
+    }
+}
+
+impl<H: ?Sized> Clone for HandlerFactory<H> {
+
+    fn clone(&self) -> Self {
+        HandlerFactory(self.0.clone())
+    }
+}
+
+impl<H: ?Sized> fmt::Debug for HandlerFactory<H> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HandlerFactory")
+    }
+}
+
+/// A stateless function registered under a command name.
+#[derive(Clone)]
+pub struct Function(Arc<dyn FunctionHandler>);
+
+impl Function {
+
+    pub fn handler(&self) -> Arc<dyn FunctionHandler> {
+        self.0.clone()
+    }
+}
+
+impl fmt::Debug for Function {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Function")
+    }
+}
+
+/// Callback invoked with the errors reported by the proxy.
+#[derive(Clone)]
+pub struct ErrorHandler(Arc<dyn Fn(&UserFunctionError) + Send + Sync>);
+
+impl ErrorHandler {
+
+    pub fn handle(&self, error: &UserFunctionError) {
+        (self.0)(error)
+    }
+}
+
+impl fmt::Debug for ErrorHandler {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ErrorHandler")
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct EntityService {
     pub entity_type: String,
     pub persistence_id: String,
     pub snapshot_every: u16,
+    pub handler: Option<HandlerFactory<dyn EventSourcedHandler>>,
+    pub crdt_handler: Option<HandlerFactory<dyn CrdtHandler>>,
+    pub functions: HashMap<String, Function>,
+    pub type_urls: Vec<String>,
+    /// Type url of the command of each method, by method name.
+    pub commands: HashMap<String, String>,
+    /// Methods and event types registered more than once, see `resolve_methods`.
+    pub duplicates: Vec<String>,
+    pub methods: HashMap<String, Method>,
 }
 
 impl Default for EntityService {
@@ -18,7 +107,14 @@ impl Default for EntityService {
         EntityService {
             entity_type: String::from(""),
             persistence_id: String::from(""),
-            snapshot_every: 0
+            snapshot_every: 0,
+            handler: None,
+            crdt_handler: None,
+            functions: HashMap::new(),
+            type_urls: Vec::new(),
+            commands: HashMap::new(),
+            duplicates: Vec::new(),
+            methods: HashMap::new(),
         }
     }
 }
@@ -39,13 +135,87 @@ impl EntityService {
         self
     }
 
+    pub fn handler<F>(&mut self, factory: F) -> &mut EntityService
+        where F: Fn(&str) -> Box<dyn EventSourcedHandler> + Send + Sync + 'static {
+        self.handler = Some(HandlerFactory(Arc::new(factory)));
+        self
+    }
+
+    /// Registers the event sourced entity that handles the commands of this service.
+    pub fn entity<E: EventSourcedEntity>(&mut self, entity: E) -> &mut EntityService {
+        let types = Arc::new(EntityTypes::new(&entity));
+        self.type_urls = types.type_urls().cloned().collect();
+        self.commands = types.commands().map(|(method, type_url)| (method.clone(), type_url.clone())).collect();
+        self.duplicates = types.duplicates().cloned().collect();
+
+        let entity = Arc::new(entity);
+        self.handler(move |_| Box::new(EntityInstance::new(entity.clone(), types.clone())) as Box<dyn EventSourcedHandler>)
+    }
+
+    pub fn crdt_handler<F>(&mut self, factory: F) -> &mut EntityService
+        where F: Fn(&str) -> Box<dyn CrdtHandler> + Send + Sync + 'static {
+        self.crdt_handler = Some(HandlerFactory(Arc::new(factory)));
+        self
+    }
+
+    pub fn function<F>(&mut self, command_name: String, handler: F) -> &mut EntityService
+        where F: FunctionHandler + 'static {
+        self.functions.insert(command_name, Function(Arc::new(handler)));
+        self
+    }
+
+    /// Loads the methods of the service from the descriptor set and checks that the
+    /// registered payload types match the declared ones.
+    pub fn resolve_methods(&mut self, service_name: &str, descriptor_set: &[u8]) -> Result<(), String> {
+        if let Some(duplicate) = self.duplicates.first() {
+            return Err(format!("Service {:?} registers {:?} more than once", service_name, duplicate));
+        }
+
+        let methods = descriptor::service_methods(descriptor_set, service_name)?;
+        let names = payload::message_names(descriptor_set)
+            .map_err(|err| format!("Unable to decode the descriptor set: {}", err))?;
+
+        for type_url in &self.type_urls {
+            if !names.iter().any(|name| payload::type_url(name) == *type_url) {
+                return Err(format!("Service {:?} registers {:?} which is not declared in the descriptor set", service_name, type_url));
+            }
+        }
+
+        if !self.commands.is_empty() {
+            for method in methods.values() {
+                let input_type = payload::type_url(&method.input_type);
+                match self.commands.get(&method.name) {
+                    None => return Err(format!("Method {:?} of service {:?} has no registered command", method.name, service_name)),
+                    Some(type_url) if *type_url != input_type => return Err(format!(
+                        "Method {:?} of service {:?} takes {:?} but its command is a {:?}", method.name, service_name, input_type, type_url,
+                    )),
+                    Some(_) => {},
+                }
+            }
+
+            for name in self.commands.keys() {
+                if !methods.contains_key(name) {
+                    return Err(format!("Command {:?} is not a method of service {:?}", name, service_name));
+                }
+            }
+        }
+
+        self.methods = methods;
+        Ok(())
+    }
+
     pub fn event_sourced(&mut self) -> EntityService {
-        self.entity_type = "cloudstate.eventsourced.EventSourced".to_string();
+        self.entity_type = EVENT_SOURCED.to_string();
         self.clone()
     }
 
     pub fn crdt(&mut self) -> EntityService {
-        self.entity_type = "cloudstate.crdt.Crdt".to_string();
+        self.entity_type = CRDT.to_string();
+        self.clone()
+    }
+
+    pub fn stateless_function(&mut self) -> EntityService {
+        self.entity_type = STATELESS_FUNCTION.to_string();
         self.clone()
     }
 
@@ -53,22 +223,40 @@ impl EntityService {
 
 #[derive(Debug)]
 pub struct CloudState {
-    entity: EntityService,
+    entities: BTreeMap<String, EntityService>,
+    descriptor: Vec<u8>,
+    error_handler: Option<ErrorHandler>,
+    metrics: Arc<Metrics>,
+    metrics_address: Option<SocketAddr>,
+    shutdown_timeout: Duration,
     service_name: String,
     service_version: String,
-    actor_system_name: String,
-    server_port: u16,
+    host: Option<IpAddr>,
+    server_port: Option<u16>,
+    unix_socket: Option<PathBuf>,
+    tls: Option<TlsConfig>,
+    health: HealthReporter,
+    entity_settings: HashMap<String, EntitySettings>,
 }
 
 impl Default for CloudState {
 
     fn default() -> CloudState {
         CloudState {
-            entity: EntityService::default(),
+            entities: BTreeMap::new(),
+            descriptor: Vec::new(),
+            error_handler: None,
+            metrics: Arc::new(Metrics::new()),
+            metrics_address: None,
+            shutdown_timeout: Duration::from_secs(30),
             service_name: String::from(""),
             service_version: String::from("0.5.0"),
-            actor_system_name: String::from("cloudstate-rust-system"),
-            server_port: 8080
+            host: None,
+            server_port: None,
+            unix_socket: None,
+            tls: None,
+            health: HealthReporter::new(),
+            entity_settings: HashMap::new(),
         }
     }
 }
@@ -79,6 +267,33 @@ impl CloudState {
         Default::default()
     }
 
+    /// Creates a CloudState configured by `config/cloudstate.toml` (or `.yaml`), or the file
+    /// named by the `CLOUDSTATE_CONFIG` environment variable, with `CLOUDSTATE_` prefixed
+    /// environment variables taking precedence. See `Settings` for the format.
+    pub fn from_config() -> Result<CloudState, Error> {
+        Settings::load().map(CloudState::with_settings)
+    }
+
+    pub fn from_config_file(path: &str) -> Result<CloudState, Error> {
+        Settings::load_file(path, true).map(CloudState::with_settings)
+    }
+
+    /// Creates a CloudState from settings. Entity settings override the
+    /// builder values of the entity services registered afterwards.
+    pub fn with_settings(settings: Settings) -> CloudState {
+        let mut cloudstate = CloudState::new();
+        cloudstate.host = settings.host;
+        cloudstate.server_port = settings.port;
+        if let Some(service_name) = settings.service_name {
+            cloudstate.service_name = service_name;
+        }
+        if let Some(service_version) = settings.service_version {
+            cloudstate.service_version = service_version;
+        }
+        cloudstate.entity_settings = settings.entities;
+        cloudstate
+    }
+
     pub fn service_name(&mut self, service_name: String) -> &mut CloudState {
         self.service_name = service_name;
         self
@@ -89,56 +304,177 @@ impl CloudState {
         self
     }
 
-    pub fn actor_system_name(&mut self, system_name: String) -> &mut CloudState {
-        self.actor_system_name = system_name;
+    /// Sets the interface to listen on, such as `::` or `127.0.0.1`.
+    /// Defaults to the `HOST` environment variable, or all IPv4 interfaces.
+    pub fn host(&mut self, host: IpAddr) -> &mut CloudState {
+        self.host = Some(host);
         self
     }
 
+    /// Defaults to the `PORT` environment variable, or 8080.
     pub fn port(&mut self, server_port: u16) -> &mut CloudState {
-        self.server_port = server_port;
+        self.server_port = Some(server_port);
         self
     }
 
-    pub fn register_entity_service(&mut self, service_name: String, entity_service: EntityService) -> &mut CloudState {
-        self.service_name = service_name;
-        self.entity = entity_service;
+    pub fn bind(&mut self, addr: SocketAddr) -> &mut CloudState {
+        self.host = Some(addr.ip());
+        self.server_port = Some(addr.port());
         self
     }
 
-    pub fn start(&mut self) -> &mut CloudState {
-        let system = self.actor_system_name.clone();
-        debug!("Create ActorSystem {:?}", system);
-        let actor_system = System::new(system);
+    /// Listens on a unix domain socket instead of TCP. Only supported on unix,
+    /// elsewhere the start fails with `Error::Config`.
+    pub fn unix_socket<P: Into<PathBuf>>(&mut self, path: P) -> &mut CloudState {
+        self.unix_socket = Some(path.into());
+        self
+    }
 
-        // start new actor
-        let addr = ProtocolHandlerActor{}.start();
+    /// Sets the `FileDescriptorSet` declaring the registered services, as generated by
+    /// `protoc --include_imports --descriptor_set_out`, usually embedded with `include_bytes!`.
+    pub fn descriptor(&mut self, descriptor_set: &[u8]) -> &mut CloudState {
+        self.descriptor = descriptor_set.to_vec();
+        self
+    }
 
-        let options = Options {
-            entity_service: self.entity.clone(),
-            service_name: self.service_name.clone(),
-            service_version: self.service_version.clone(),
-            server_port: self.server_port
-        };
+    /// Registers a callback for the errors reported by the proxy, for instance to raise
+    /// an alert or to mark the service unhealthy.
+    pub fn on_error<F>(&mut self, handler: F) -> &mut CloudState
+        where F: Fn(&UserFunctionError) + Send + Sync + 'static {
+        self.error_handler = Some(ErrorHandler(Arc::new(handler)));
+        self
+    }
 
-        let msg = StartMessage {
-            opts: options,
-        };
-        
-        // send message and get future for result
-        let res = addr.send(msg);
+    /// Serves all the services over TLS, reloading the certificate when its files change.
+    ///
+    /// ```ignore
+    /// cloudstate.tls(TlsConfig::new("tls/server.crt", "tls/server.key").client_ca("tls/ca.crt"));
+    /// ```
+    pub fn tls(&mut self, tls: TlsConfig) -> &mut CloudState {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Time given to the in-flight commands to complete on shutdown. Defaults to 30 seconds.
+    pub fn shutdown_timeout(&mut self, timeout: Duration) -> &mut CloudState {
+        self.shutdown_timeout = timeout;
+        self
+    }
 
-        Arbiter::spawn(
-            res.map(|_res| {
-                info!("System started!");
-            })
-            .map_err(|_| ())
-        );
+    /// Handle to the statuses served by `grpc.health.v1.Health`, one per entity service.
+    pub fn health(&self) -> HealthReporter {
+        self.health.clone()
+    }
 
-        actor_system.run()
-            .map_err(|err| error!("Error on start ActorSystem. Error: {:?}", err))
-            .ok();
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
 
+    /// Serves the metrics in the Prometheus text format over HTTP on `addr`,
+    /// next to the gRPC services.
+    #[cfg(feature = "prometheus")]
+    pub fn metrics_address(&mut self, addr: SocketAddr) -> &mut CloudState {
+        self.metrics_address = Some(addr);
         self
     }
 
+    pub fn register_entity_service(&mut self, service_name: String, mut entity_service: EntityService) -> &mut CloudState {
+        if let Some(settings) = self.entity_settings.get(&service_name) {
+            if let Some(persistence_id) = settings.persistence_id.clone() {
+                entity_service.persistence_id(persistence_id);
+            }
+            if let Some(snapshot_every) = settings.snapshot_every {
+                entity_service.snapshot(snapshot_every);
+            }
+        }
+
+        if self.entities.insert(service_name.clone(), entity_service).is_some() {
+            warn!("Entity service {:?} registered twice. Keeping the last one", service_name);
+        }
+        self
+    }
+
+    /// Validates the registered entity services against the descriptor set and
+    /// builds the options shared by the gRPC services.
+    pub(crate) fn options(&self) -> Result<Options, Error> {
+        let options = self.entity_options()?;
+        Ok(Options { address: self.address()?, ..options })
+    }
+
+    /// Same as `options` except for the address to listen on, left to the default without
+    /// reading `HOST` and `PORT`. Enough to drive the entity streams in process, as the testkit does.
+    pub(crate) fn entity_options(&self) -> Result<Options, Error> {
+        if self.descriptor.is_empty() {
+            return Err(Error::Descriptor(String::from("No descriptor set registered. Set one with CloudState::descriptor")));
+        }
+
+        let mut entity_services = self.entities.clone();
+        for (service_name, entity_service) in entity_services.iter_mut() {
+            entity_service.resolve_methods(service_name, &self.descriptor)
+                .map_err(|err| Error::Descriptor(format!("Invalid entity service {:?}. Error: {}", service_name, err)))?;
+            self.health.register(service_name);
+        }
+
+        // Without an explicit name the service is named after its first entity
+        let service_name = if self.service_name.is_empty() {
+            self.entities.keys().next().cloned().unwrap_or_default()
+        } else {
+            self.service_name.clone()
+        };
+
+        Ok(Options {
+            entity_services,
+            descriptor: self.descriptor.clone(),
+            error_handler: self.error_handler.clone(),
+            metrics: self.metrics.clone(),
+            shutdown: Arc::new(Shutdown::new()),
+            shutdown_timeout: self.shutdown_timeout,
+            metrics_address: self.metrics_address,
+            service_name,
+            service_version: self.service_version.clone(),
+            address: listener::Address::Tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), listener::DEFAULT_PORT)),
+            tls: self.tls.clone(),
+            health: self.health.clone(),
+        })
+    }
+
+    // Resolves HOST when set in the environment
+    fn address(&self) -> Result<listener::Address, Error> {
+        match self.unix_socket.as_ref() {
+            Some(_) if self.tls.is_some() => Err(Error::Config(String::from("TLS is not supported on unix domain sockets"))),
+            #[cfg(unix)]
+            Some(path) => Ok(listener::Address::Unix(path.clone())),
+            #[cfg(not(unix))]
+            Some(_) => Err(Error::Config(String::from("Unix domain sockets are only supported on unix"))),
+            None => listener::tcp_address(self.host, self.server_port).map_err(Error::Config),
+        }
+    }
+
+    /// Serves the registered entity services on the tokio runtime of the caller,
+    /// shutting down gracefully on SIGINT or SIGTERM.
+    ///
+    /// ```ignore
+    /// let mut cloudstate = CloudState::new();
+    /// cloudstate.descriptor(DESCRIPTOR).register_entity_service(name, service);
+    /// cloudstate.run().await?;
+    /// ```
+    pub async fn run(self) -> Result<(), Error> {
+        let options = self.options()?;
+        GrpcServer::new(options).serve_with_shutdown(shutdown::signal()).await
+    }
+
+    /// Like `run`, but shuts down once `signal` completes instead of on SIGINT or SIGTERM.
+    pub async fn serve_with_shutdown<F>(self, signal: F) -> Result<(), Error>
+        where F: std::future::Future<Output = ()> {
+        let options = self.options()?;
+        GrpcServer::new(options).serve_with_shutdown(signal).await
+    }
+
+    /// Blocks the current thread serving the registered entity services on a new tokio runtime,
+    /// shutting down gracefully on SIGINT or SIGTERM.
+    pub fn start(&mut self) -> Result<(), Error> {
+        let cloudstate = mem::take(self);
+        Runtime::new()?.block_on(cloudstate.run())
+    }
+
 }
\ No newline at end of file
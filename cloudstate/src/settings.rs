@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::env;
+use std::net::IpAddr;
+use config::{Config, ConfigError, Environment, File, Value};
+use crate::error::Error;
+
+/// Environment variable holding the path of the configuration file.
+pub const CONFIG_ENV: &str = "CLOUDSTATE_CONFIG";
+/// Configuration file read when `CLOUDSTATE_CONFIG` is unset, with any supported extension.
+pub const DEFAULT_CONFIG: &str = "config/cloudstate";
+/// Prefix of the environment variables overriding the file, e.g. `CLOUDSTATE_PORT=9000`.
+pub const ENV_PREFIX: &str = "CLOUDSTATE";
+
+/// Settings of an entity service, applied when it is registered.
+#[derive(Debug, Clone, Default)]
+pub struct EntitySettings {
+    pub persistence_id: Option<String>,
+    pub snapshot_every: Option<u16>,
+}
+
+/// Deployment settings read from a TOML or YAML file such as
+///
+/// ```toml
+/// port = 8080
+/// service_name = "shopping-cart"
+/// service_version = "1.0.0"
+///
+/// [[entities]]
+/// service = "com.example.shoppingcart.ShoppingCart"
+/// persistence_id = "shopping-cart"
+/// snapshot_every = 100
+/// ```
+///
+/// Top level settings are overridden by `CLOUDSTATE_` prefixed environment variables.
+/// The `[[entities]]` are only read from the file, no environment variable overrides them.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    pub host: Option<IpAddr>,
+    pub port: Option<u16>,
+    pub service_name: Option<String>,
+    pub service_version: Option<String>,
+    pub entities: HashMap<String, EntitySettings>,
+}
+
+impl Settings {
+
+    /// Reads the file named by `CLOUDSTATE_CONFIG`, or `config/cloudstate` if it exists.
+    pub fn load() -> Result<Settings, Error> {
+        match env::var(CONFIG_ENV) {
+            Ok(path) => Settings::load_file(&path, true),
+            Err(_) => Settings::load_file(DEFAULT_CONFIG, false),
+        }
+    }
+
+    pub fn load_file(path: &str, required: bool) -> Result<Settings, Error> {
+        let mut config = Config::new();
+        config.merge(File::with_name(path).required(required))
+            .map_err(|err| Error::Config(format!("Unable to read configuration {:?}: {}", path, err)))?;
+        config.merge(Environment::with_prefix(ENV_PREFIX))
+            .map_err(|err| Error::Config(format!("Invalid configuration environment: {}", err)))?;
+
+        Settings::read(&config).map_err(Error::Config)
+    }
+
+    fn read(config: &Config) -> Result<Settings, String> {
+        let host = match optional(config.get_str("host"), "host")? {
+            Some(host) => Some(host.parse().map_err(|_| format!("Invalid host {:?}", host))?),
+            None => None,
+        };
+
+        let mut entities = HashMap::new();
+        for entity in optional(config.get_array("entities"), "entities")?.unwrap_or_default() {
+            let (service, settings) = entity_settings(entity)?;
+            entities.insert(service, settings);
+        }
+
+        Ok(Settings {
+            host,
+            port: integer(optional(config.get_int("port"), "port")?, "port")?,
+            service_name: optional(config.get_str("service_name"), "service_name")?,
+            service_version: optional(config.get_str("service_version"), "service_version")?,
+            entities,
+        })
+    }
+}
+
+fn entity_settings(entity: Value) -> Result<(String, EntitySettings), String> {
+    let mut table = entity.into_table()
+        .map_err(|err| format!("Invalid entities setting: {}", err))?;
+
+    let service = match table.remove("service") {
+        Some(service) => service.into_str().map_err(|err| format!("Invalid entity service: {}", err))?,
+        None => return Err(String::from("Missing service of entities setting")),
+    };
+    let persistence_id = match table.remove("persistence_id") {
+        Some(id) => Some(id.into_str().map_err(|err| format!("Invalid persistence_id of {:?}: {}", service, err))?),
+        None => None,
+    };
+    let snapshot_every = match table.remove("snapshot_every") {
+        Some(every) => Some(every.into_int().map_err(|err| format!("Invalid snapshot_every of {:?}: {}", service, err))?),
+        None => None,
+    };
+
+    let settings = EntitySettings {
+        persistence_id,
+        snapshot_every: integer(snapshot_every, "snapshot_every")?,
+    };
+    Ok((service, settings))
+}
+
+// Missing settings keep the defaults of the builders
+fn optional<T>(value: Result<T, ConfigError>, key: &str) -> Result<Option<T>, String> {
+    match value {
+        Ok(value) => Ok(Some(value)),
+        Err(ConfigError::NotFound(_)) => Ok(None),
+        Err(err) => Err(format!("Invalid {} setting: {}", key, err)),
+    }
+}
+
+fn integer(value: Option<i64>, key: &str) -> Result<Option<u16>, String> {
+    match value {
+        Some(value) => u16::try_from(value)
+            .map(Some)
+            .map_err(|_| format!("Invalid {} setting: {} is out of range", key, value)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Config, File, FileFormat};
+    use super::Settings;
+
+    fn read(toml: &str) -> Result<Settings, String> {
+        let mut config = Config::new();
+        config.merge(File::from_str(toml, FileFormat::Toml)).unwrap();
+        Settings::read(&config)
+    }
+
+    #[test]
+    fn read_settings() {
+        let settings = read(r#"
+            host = "127.0.0.1"
+            port = 9000
+            service_name = "shopping-cart"
+            service_version = "1.0.0"
+
+            [[entities]]
+            service = "com.example.shoppingcart.ShoppingCart"
+            persistence_id = "shopping-cart"
+            snapshot_every = 100
+
+            [[entities]]
+            service = "com.example.Counter"
+        "#).unwrap();
+
+        assert_eq!(settings.host, Some("127.0.0.1".parse().unwrap()));
+        assert_eq!(settings.port, Some(9000));
+        assert_eq!(settings.service_name, Some(String::from("shopping-cart")));
+        assert_eq!(settings.service_version, Some(String::from("1.0.0")));
+
+        let cart = &settings.entities["com.example.shoppingcart.ShoppingCart"];
+        assert_eq!(cart.persistence_id, Some(String::from("shopping-cart")));
+        assert_eq!(cart.snapshot_every, Some(100));
+        let counter = &settings.entities["com.example.Counter"];
+        assert_eq!(counter.persistence_id, None);
+        assert_eq!(counter.snapshot_every, None);
+    }
+
+    #[test]
+    fn missing_settings_are_unset() {
+        let settings = read("").unwrap();
+        assert_eq!(settings.host, None);
+        assert_eq!(settings.port, None);
+        assert_eq!(settings.service_name, None);
+        assert!(settings.entities.is_empty());
+    }
+
+    #[test]
+    fn reject_invalid_settings() {
+        let invalid = [
+            r#"host = "not an address""#,
+            "port = 65536",
+            "port = -1",
+            "[[entities]]\npersistence_id = \"shopping-cart\"",
+            "[[entities]]\nservice = \"com.example.Counter\"\nsnapshot_every = 100000",
+        ];
+        for toml in invalid.iter() {
+            assert!(read(toml).is_err(), "{:?}", toml);
+        }
+    }
+}
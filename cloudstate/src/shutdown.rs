@@ -0,0 +1,265 @@
+use std::io::Cursor;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::StreamExt;
+use futures_util::future::{self, Either};
+use futures_util::task::AtomicWaker;
+use http::{HeaderMap, Response};
+use tokio::sync::mpsc;
+use tokio::timer::delay_for;
+use tonic::{Code, Status};
+use tonic::body::BoxBody;
+use tonic::codegen::HttpBody;
+
+/// Tracks the commands being handled, so that the server can let them
+/// complete before stopping.
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    draining: AtomicBool,
+    in_flight: AtomicUsize,
+    // Woken by the last in-flight guard to drop
+    drained: AtomicWaker,
+}
+
+impl Shutdown {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Stops accepting new streams and calls, and new commands on the open streams.
+    pub fn drain(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Rejects new streams, calls and commands once the server is draining.
+    pub fn accept(&self) -> Result<(), Status> {
+        if self.is_draining() {
+            Err(Status::new(Code::Unavailable, "The user function is shutting down"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Marks a command as in flight until the returned guard is dropped.
+    pub fn begin(self: &Arc<Self>) -> InFlight {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight(self.clone())
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Completes once no command is in flight anymore. Awaited by a single task at a time,
+    /// the one stopping the server.
+    pub async fn drained(&self) {
+        future::poll_fn(|cx| {
+            // Registered before checking, so that a guard dropped in between still wakes the task
+            self.drained.register(cx.waker());
+            if self.in_flight() == 0 {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }).await
+    }
+}
+
+/// Guard of a command being handled, see `Shutdown::begin`.
+#[derive(Debug)]
+pub struct InFlight(Arc<Shutdown>);
+
+impl Drop for InFlight {
+
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.drained.wake();
+        }
+    }
+}
+
+/// Creates the reply stream of a streamed call, see `Replies`.
+pub fn replies<T>(shutdown: &Arc<Shutdown>, buffer: usize) -> (ReplySender<T>, Replies<T>) {
+    let (tx, rx) = mpsc::channel(buffer);
+    let sender = ReplySender {
+        tx,
+        shutdown: shutdown.clone(),
+    };
+    (sender, Replies { rx, sent: None })
+}
+
+/// Sending half of a `Replies` stream.
+#[derive(Debug)]
+pub struct ReplySender<T> {
+    tx: mpsc::Sender<(Result<T, Status>, InFlight)>,
+    shutdown: Arc<Shutdown>,
+}
+
+impl<T> Clone for ReplySender<T> {
+
+    fn clone(&self) -> Self {
+        ReplySender {
+            tx: self.tx.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+}
+
+impl<T> ReplySender<T> {
+
+    /// Queues a reply, fails once the proxy closed the stream.
+    pub async fn send(&mut self, reply: Result<T, Status>) -> Result<(), mpsc::error::SendError> {
+        let in_flight = self.shutdown.begin();
+        self.tx.send((reply, in_flight)).await
+    }
+}
+
+/// Replies of a streamed call. A reply stays in flight from being queued until the
+/// transport polls for the next one, by then it has been encoded into the response.
+#[derive(Debug)]
+pub struct Replies<T> {
+    rx: mpsc::Receiver<(Result<T, Status>, InFlight)>,
+    sent: Option<InFlight>,
+}
+
+impl<T> Stream for Replies<T> {
+    type Item = Result<T, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.sent = None;
+        match self.rx.poll_recv(cx) {
+            Poll::Ready(Some((reply, in_flight))) => {
+                self.sent = Some(in_flight);
+                Poll::Ready(Some(reply))
+            },
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Keeps a call answering with a single reply in flight until its response has been written.
+pub fn track(response: Response<BoxBody>, in_flight: InFlight) -> Response<BoxBody> {
+    response.map(|body| BoxBody::new(TrackedBody { body, _in_flight: in_flight }))
+}
+
+/// Response body releasing its guard once dropped by the transport.
+struct TrackedBody {
+    body: BoxBody,
+    _in_flight: InFlight,
+}
+
+impl HttpBody for TrackedBody {
+    type Data = Cursor<Bytes>;
+    type Error = Status;
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn poll_data(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Pin::new(&mut self.body).poll_data(cx)
+    }
+
+    fn poll_trailers(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.body).poll_trailers(cx)
+    }
+}
+
+/// Completes when the process receives SIGINT or, on unix, SIGTERM.
+pub async fn signal() {
+    let ctrl_c = async {
+        match tokio::net::signal::ctrl_c() {
+            Ok(mut ctrl_c) => {
+                ctrl_c.next().await;
+            },
+            Err(err) => {
+                warn!("Unable to listen for SIGINT. Error: {:?}", err);
+                future::pending::<()>().await;
+            },
+        }
+    };
+
+    future::select(Box::pin(ctrl_c), Box::pin(terminate())).await;
+}
+
+#[cfg(unix)]
+async fn terminate() {
+    use tokio_net::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            sigterm.next().await;
+        },
+        Err(err) => {
+            warn!("Unable to listen for SIGTERM. Error: {:?}", err);
+            future::pending::<()>().await;
+        },
+    }
+}
+
+#[cfg(not(unix))]
+async fn terminate() {
+    future::pending::<()>().await;
+}
+
+/// Drains the in-flight commands, giving up after `timeout`.
+pub async fn drain(shutdown: &Shutdown, timeout: Duration) {
+    shutdown.drain();
+    info!("Draining {} in-flight commands", shutdown.in_flight());
+
+    match future::select(Box::pin(shutdown.drained()), Box::pin(delay_for(timeout))).await {
+        Either::Left(_) => info!("All in-flight commands completed"),
+        Either::Right(_) => warn!("Shutdown deadline of {:?} exceeded with {} commands still in flight", timeout, shutdown.in_flight()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll};
+    use futures_util::task::{self, ArcWake};
+    use super::Shutdown;
+
+    #[derive(Default)]
+    struct Wakes(AtomicUsize);
+
+    impl ArcWake for Wakes {
+
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn drained_once_the_last_command_completes() {
+        let shutdown = Arc::new(Shutdown::new());
+        let (first, second) = (shutdown.begin(), shutdown.begin());
+        shutdown.drain();
+        assert!(shutdown.accept().is_err());
+
+        let wakes = Arc::new(Wakes::default());
+        let waker = task::waker(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut drained = Box::pin(shutdown.drained());
+        assert_eq!(drained.as_mut().poll(&mut cx), Poll::Pending);
+
+        drop(first);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+        drop(second);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+        assert_eq!(drained.as_mut().poll(&mut cx), Poll::Ready(()));
+    }
+}
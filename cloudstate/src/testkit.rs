@@ -0,0 +1,165 @@
+use prost_types::Any;
+use crate::error::Error;
+use crate::eventsourced::EntityStream;
+use crate::payload::{self, MessageName, PayloadError};
+use crate::protocol::spec::{client_action, Command, Forward, SideEffect};
+use crate::protocol::spec::eventsourced::{
+    event_sourced_stream_in, event_sourced_stream_out,
+    EventSourcedEvent, EventSourcedInit, EventSourcedReply, EventSourcedSnapshot, EventSourcedStreamIn,
+};
+use crate::serveless::CloudState;
+
+/// Drives an event sourced entity registered on a `CloudState` the way the proxy does,
+/// in process, so that the entity logic can be unit tested with `cargo test`.
+/// Enabled by the `testkit` feature, usually on the dev-dependency of the user function.
+///
+/// ```ignore
+/// let mut cloudstate = CloudState::new();
+/// cloudstate.descriptor(DESCRIPTOR).register_entity_service(name.clone(), service);
+///
+/// let mut cart = EventSourcedTestKit::new(&cloudstate, &name, "cart-1")?;
+/// cart.replay(payload::pack(&added))?;
+/// let reply = cart.command("GetCart", payload::pack(&get))?;
+/// let items: Cart = reply.reply_as()?;
+/// ```
+pub struct EventSourcedTestKit {
+    stream: EntityStream,
+    entity_id: String,
+    sequence: i64,
+    next_command_id: i64,
+}
+
+impl EventSourcedTestKit {
+
+    /// Starts the entity `entity_id` of the service `service_name` without a snapshot.
+    pub fn new(cloudstate: &CloudState, service_name: &str, entity_id: &str) -> Result<Self, Error> {
+        EventSourcedTestKit::init(cloudstate, service_name, entity_id, None)
+    }
+
+    /// Starts the entity from a snapshot taken at `sequence`.
+    pub fn from_snapshot(cloudstate: &CloudState, service_name: &str, entity_id: &str, sequence: i64, snapshot: Any) -> Result<Self, Error> {
+        let snapshot = EventSourcedSnapshot {
+            snapshot_sequence: sequence,
+            snapshot: Some(snapshot),
+        };
+        EventSourcedTestKit::init(cloudstate, service_name, entity_id, Some(snapshot))
+    }
+
+    fn init(cloudstate: &CloudState, service_name: &str, entity_id: &str, snapshot: Option<EventSourcedSnapshot>) -> Result<Self, Error> {
+        let mut testkit = EventSourcedTestKit {
+            stream: EntityStream::new(cloudstate.entity_options()?),
+            entity_id: entity_id.to_string(),
+            sequence: snapshot.as_ref().map_or(0, |snapshot| snapshot.snapshot_sequence),
+            next_command_id: 1,
+        };
+
+        testkit.send(event_sourced_stream_in::Message::Init(EventSourcedInit {
+            service_name: service_name.to_string(),
+            entity_id: entity_id.to_string(),
+            snapshot,
+        }))?;
+        Ok(testkit)
+    }
+
+    /// Replays a persisted event, numbered after the snapshot and the previous events.
+    pub fn replay(&mut self, event: Any) -> Result<(), Error> {
+        self.sequence += 1;
+        self.send(event_sourced_stream_in::Message::Event(EventSourcedEvent {
+            sequence: self.sequence,
+            payload: Some(event),
+        }))?;
+        Ok(())
+    }
+
+    /// Sends a call of the method `name`. An error is a failure closing the entity stream,
+    /// a failure returned by the command handler is part of the reply.
+    pub fn command(&mut self, name: &str, payload: Any) -> Result<CommandReply, Error> {
+        let command = Command {
+            entity_id: self.entity_id.clone(),
+            id: self.next_command_id,
+            name: name.to_string(),
+            payload: Some(payload),
+            streamed: false,
+        };
+        self.next_command_id += 1;
+
+        match self.send(event_sourced_stream_in::Message::Command(command))? {
+            Some(event_sourced_stream_out::Message::Reply(reply)) => {
+                self.sequence += reply.events.len() as i64;
+                Ok(CommandReply(reply))
+            },
+            _ => Err(Error::Protocol(format!("Entity {:?} did not reply to command {:?}", self.entity_id, name))),
+        }
+    }
+
+    /// Sequence number of the last event, replayed or emitted.
+    pub fn sequence(&self) -> i64 {
+        self.sequence
+    }
+
+    fn send(&mut self, message: event_sourced_stream_in::Message) -> Result<Option<event_sourced_stream_out::Message>, Error> {
+        self.stream.handle_message(EventSourcedStreamIn { message: Some(message) })
+            .map(|out| out.and_then(|out| out.message))
+            .map_err(|failure| Error::Protocol(failure.description))
+    }
+}
+
+/// Reply of the entity to a command sent by `EventSourcedTestKit::command`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandReply(pub EventSourcedReply);
+
+impl CommandReply {
+
+    /// Payload of the reply, unless the command was forwarded or failed.
+    pub fn reply(&self) -> Option<&Any> {
+        match self.action() {
+            Some(client_action::Action::Reply(reply)) => reply.payload.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn reply_as<M: MessageName + Default>(&self) -> Result<M, PayloadError> {
+        payload::unpack(self.reply().ok_or(PayloadError::Missing)?)
+    }
+
+    pub fn forward(&self) -> Option<&Forward> {
+        match self.action() {
+            Some(client_action::Action::Forward(forward)) => Some(forward),
+            _ => None,
+        }
+    }
+
+    /// Description of the failure returned by the command handler.
+    pub fn failure(&self) -> Option<&str> {
+        match self.action() {
+            Some(client_action::Action::Failure(failure)) => Some(&failure.description),
+            _ => None,
+        }
+    }
+
+    /// Events emitted by the command, in order.
+    pub fn events(&self) -> &[Any] {
+        &self.0.events
+    }
+
+    pub fn event_as<M: MessageName + Default>(&self, index: usize) -> Result<M, PayloadError> {
+        payload::unpack(self.0.events.get(index).ok_or(PayloadError::Missing)?)
+    }
+
+    pub fn side_effects(&self) -> &[SideEffect] {
+        &self.0.side_effects
+    }
+
+    /// Snapshot taken after the command, once `snapshot_every` events were emitted.
+    pub fn snapshot(&self) -> Option<&Any> {
+        self.0.snapshot.as_ref()
+    }
+
+    pub fn into_inner(self) -> EventSourcedReply {
+        self.0
+    }
+
+    fn action(&self) -> Option<&client_action::Action> {
+        self.0.client_action.as_ref().and_then(|action| action.action.as_ref())
+    }
+}
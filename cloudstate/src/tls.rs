@@ -0,0 +1,336 @@
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::timer::delay_for;
+use tokio_rustls::rustls::{
+    AllowAnyAuthenticatedClient, Certificate, ClientCertVerified, ClientCertVerifier, DistinguishedNames,
+    NoClientAuth, PrivateKey, ResolvesServerCert, RootCertStore, ServerConfig, SignatureScheme, TLSError,
+};
+use tokio_rustls::rustls::internal::pemfile;
+use tokio_rustls::rustls::sign::{self, CertifiedKey};
+use tokio_rustls::webpki::DNSNameRef;
+
+const ALPN_H2: &[u8] = b"h2";
+
+/// PEM files of the server certificate, its private key and, for mutual TLS,
+/// of the certificate authority the clients must present a certificate from.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    cert: PathBuf,
+    key: PathBuf,
+    client_ca: Option<PathBuf>,
+    reload_interval: Duration,
+}
+
+impl TlsConfig {
+
+    pub fn new(cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        TlsConfig {
+            cert: cert.into(),
+            key: key.into(),
+            client_ca: None,
+            reload_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Requires the clients to authenticate with a certificate signed by this authority.
+    pub fn client_ca<P: Into<PathBuf>>(mut self, client_ca: P) -> Self {
+        self.client_ca = Some(client_ca.into());
+        self
+    }
+
+    /// How often the certificate, key and client CA files are checked for changes. Defaults to a minute.
+    pub fn reload_interval(mut self, interval: Duration) -> Self {
+        self.reload_interval = interval;
+        self
+    }
+
+    /// Builds the rustls configuration, together with the files it serves and
+    /// verifies the clients with, to keep up to date with `watch`.
+    pub fn server_config(&self) -> Result<(ServerConfig, TlsFiles), String> {
+        let client_ca = match self.client_ca.as_ref() {
+            Some(path) => Some(Arc::new(ClientCaVerifier::load(path.clone())?)),
+            None => None,
+        };
+        let mut config = match client_ca.as_ref() {
+            Some(verifier) => ServerConfig::new(verifier.clone()),
+            None => ServerConfig::new(NoClientAuth::new()),
+        };
+
+        let cert = Arc::new(CertResolver::load(self.cert.clone(), self.key.clone())?);
+        config.cert_resolver = cert.clone();
+        config.set_protocols(&[ALPN_H2.to_vec()]);
+        Ok((config, TlsFiles { cert, client_ca }))
+    }
+
+    /// Reloads the certificate and the client CA whenever their files change. Never completes.
+    pub async fn watch(self, files: TlsFiles) {
+        loop {
+            delay_for(self.reload_interval).await;
+            if let Err(err) = files.cert.reload_if_changed() {
+                error!("Unable to reload the TLS certificate. Keeping the current one. Error: {}", err);
+            }
+            if let Some(Err(err)) = files.client_ca.as_ref().map(|client_ca| client_ca.reload_if_changed()) {
+                error!("Unable to reload the client CA certificate. Keeping the current one. Error: {}", err);
+            }
+        }
+    }
+}
+
+/// The files of a `TlsConfig` in use by a server, see `TlsConfig::server_config`.
+pub struct TlsFiles {
+    cert: Arc<CertResolver>,
+    client_ca: Option<Arc<ClientCaVerifier>>,
+}
+
+/// Serves the last certificate loaded from the files.
+pub struct CertResolver {
+    cert: PathBuf,
+    key: PathBuf,
+    modified: Mutex<Option<SystemTime>>,
+    certified_key: RwLock<CertifiedKey>,
+}
+
+impl CertResolver {
+
+    fn load(cert: PathBuf, key: PathBuf) -> Result<Self, String> {
+        let modified = last_modified(&[&cert, &key]);
+        let certified_key = load_certified_key(&cert, &key)?;
+
+        Ok(CertResolver {
+            cert,
+            key,
+            modified: Mutex::new(modified),
+            certified_key: RwLock::new(certified_key),
+        })
+    }
+
+    fn reload_if_changed(&self) -> Result<(), String> {
+        let modified = last_modified(&[&self.cert, &self.key]);
+        let mut last = self.modified.lock().unwrap();
+        if modified == *last {
+            return Ok(());
+        }
+
+        let certified_key = load_certified_key(&self.cert, &self.key)?;
+        *self.certified_key.write().unwrap() = certified_key;
+        *last = modified;
+        info!("Reloaded the TLS certificate from {:?}", self.cert);
+        Ok(())
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+
+    fn resolve(&self, _server_name: Option<DNSNameRef>, _sigschemes: &[SignatureScheme]) -> Option<CertifiedKey> {
+        self.certified_key.read().ok().map(|key| key.clone())
+    }
+}
+
+/// Verifies the client certificates against the last CA loaded from the file.
+pub struct ClientCaVerifier {
+    path: PathBuf,
+    modified: Mutex<Option<SystemTime>>,
+    verifier: RwLock<Arc<dyn ClientCertVerifier>>,
+}
+
+impl ClientCaVerifier {
+
+    fn load(path: PathBuf) -> Result<Self, String> {
+        let modified = last_modified(&[&path]);
+        let verifier = load_client_ca(&path)?;
+
+        Ok(ClientCaVerifier {
+            path,
+            modified: Mutex::new(modified),
+            verifier: RwLock::new(verifier),
+        })
+    }
+
+    fn reload_if_changed(&self) -> Result<(), String> {
+        let modified = last_modified(&[&self.path]);
+        let mut last = self.modified.lock().unwrap();
+        if modified == *last {
+            return Ok(());
+        }
+
+        let verifier = load_client_ca(&self.path)?;
+        *self.verifier.write().unwrap() = verifier;
+        *last = modified;
+        info!("Reloaded the client CA certificate from {:?}", self.path);
+        Ok(())
+    }
+
+    fn current(&self) -> Arc<dyn ClientCertVerifier> {
+        self.verifier.read().unwrap().clone()
+    }
+}
+
+impl ClientCertVerifier for ClientCaVerifier {
+
+    fn client_auth_mandatory(&self) -> bool {
+        true
+    }
+
+    fn client_auth_root_subjects(&self) -> DistinguishedNames {
+        self.current().client_auth_root_subjects()
+    }
+
+    fn verify_client_cert(&self, presented_certs: &[Certificate]) -> Result<ClientCertVerified, TLSError> {
+        self.current().verify_client_cert(presented_certs)
+    }
+}
+
+// Latest modification of the files, the key and the certificate are usually replaced together
+fn last_modified(paths: &[&Path]) -> Option<SystemTime> {
+    let modified = |path: &&Path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    paths.iter().map(modified).max().unwrap_or(None)
+}
+
+fn load_client_ca(path: &Path) -> Result<Arc<dyn ClientCertVerifier>, String> {
+    let mut roots = RootCertStore::empty();
+    let (valid, _) = roots.add_pem_file(&mut BufReader::new(open(path)?))
+        .map_err(|_| format!("Invalid client CA certificate {:?}", path))?;
+    if valid == 0 {
+        return Err(format!("No client CA certificate found in {:?}", path));
+    }
+    Ok(AllowAnyAuthenticatedClient::new(roots))
+}
+
+fn load_certified_key(cert: &Path, key: &Path) -> Result<CertifiedKey, String> {
+    let certs: Vec<Certificate> = pemfile::certs(&mut BufReader::new(open(cert)?))
+        .map_err(|_| format!("Invalid certificate {:?}", cert))?;
+    if certs.is_empty() {
+        return Err(format!("No certificate found in {:?}", cert));
+    }
+
+    let private_key = load_private_key(key)?;
+    let signing_key = sign::any_supported_type(&private_key)
+        .map_err(|_| format!("Unsupported private key {:?}", key))?;
+
+    Ok(CertifiedKey::new(certs, Arc::new(signing_key)))
+}
+
+fn load_private_key(path: &Path) -> Result<PrivateKey, String> {
+    let pkcs8 = pemfile::pkcs8_private_keys(&mut BufReader::new(open(path)?))
+        .map_err(|_| format!("Invalid private key {:?}", path))?;
+    let rsa = pemfile::rsa_private_keys(&mut BufReader::new(open(path)?))
+        .map_err(|_| format!("Invalid private key {:?}", path))?;
+
+    pkcs8.into_iter().chain(rsa).next()
+        .ok_or_else(|| format!("No private key found in {:?}", path))
+}
+
+fn open(path: &Path) -> Result<File, String> {
+    File::open(path).map_err(|err| format!("Unable to open {:?}: {}", path, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::BufReader;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+    use tokio_rustls::rustls::{Certificate, ResolvesServerCert};
+    use tokio_rustls::rustls::internal::pemfile;
+    use tokio_rustls::rustls::ClientCertVerifier;
+    use super::{CertResolver, ClientCaVerifier, TlsConfig};
+
+    // Self signed certificates of localhost, valid for a century
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/tls").join(name)
+    }
+
+    fn copy(name: &str, to: &Path) {
+        fs::copy(fixture(name), to).unwrap();
+    }
+
+    fn cert(path: &Path) -> Certificate {
+        pemfile::certs(&mut BufReader::new(File::open(path).unwrap())).unwrap().remove(0)
+    }
+
+    fn served(resolver: &CertResolver) -> Certificate {
+        resolver.resolve(None, &[]).unwrap().cert.remove(0)
+    }
+
+    // Pushes the modification time forward, as a renewal would
+    fn touch(path: &Path, seconds: u64) {
+        let modified = SystemTime::now() + Duration::from_secs(seconds);
+        File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn load_the_pem_files() {
+        let config = TlsConfig::new(fixture("server.crt"), fixture("server.key"));
+        let (server_config, files) = config.server_config().unwrap();
+        assert_eq!(server_config.alpn_protocols, vec![b"h2".to_vec()]);
+        assert_eq!(served(&files.cert), cert(&fixture("server.crt")));
+        assert!(files.client_ca.is_none());
+
+        let missing = TlsConfig::new(fixture("server.crt"), fixture("missing.key"));
+        assert!(missing.server_config().is_err());
+        let not_a_key = TlsConfig::new(fixture("server.crt"), fixture("server.crt"));
+        match not_a_key.server_config() {
+            Err(err) => assert!(err.contains("No private key found"), "{}", err),
+            Ok(_) => panic!("Loaded a certificate as the private key"),
+        }
+    }
+
+    #[test]
+    fn reload_when_the_files_change() {
+        let dir = env::temp_dir().join(format!("cloudstate-tls-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (cert_path, key_path) = (dir.join("server.crt"), dir.join("server.key"));
+        copy("server.crt", &cert_path);
+        copy("server.key", &key_path);
+
+        let resolver = CertResolver::load(cert_path.clone(), key_path.clone()).unwrap();
+        resolver.reload_if_changed().unwrap();
+        assert_eq!(served(&resolver), cert(&fixture("server.crt")));
+
+        // An invalid certificate keeps the current one served
+        fs::write(&cert_path, "").unwrap();
+        touch(&cert_path, 10);
+        assert!(resolver.reload_if_changed().is_err());
+        assert_eq!(served(&resolver), cert(&fixture("server.crt")));
+
+        copy("renewed.crt", &cert_path);
+        copy("renewed.key", &key_path);
+        touch(&cert_path, 20);
+        touch(&key_path, 20);
+        resolver.reload_if_changed().unwrap();
+        assert_eq!(served(&resolver), cert(&fixture("renewed.crt")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reload_the_client_ca_when_its_file_changes() {
+        let dir = env::temp_dir().join(format!("cloudstate-tls-ca-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let ca_path = dir.join("ca.crt");
+        copy("server.crt", &ca_path);
+
+        let verifier = ClientCaVerifier::load(ca_path.clone()).unwrap();
+        assert!(verifier.client_auth_mandatory());
+        assert_eq!(verifier.client_auth_root_subjects().len(), 1);
+
+        // An invalid CA keeps the current one trusted
+        fs::write(&ca_path, "").unwrap();
+        touch(&ca_path, 10);
+        assert!(verifier.reload_if_changed().is_err());
+        assert_eq!(verifier.client_auth_root_subjects().len(), 1);
+
+        // A bundle with the renewed CA next to the current one
+        let bundle = [fs::read(fixture("server.crt")).unwrap(), fs::read(fixture("renewed.crt")).unwrap()].concat();
+        fs::write(&ca_path, bundle).unwrap();
+        touch(&ca_path, 20);
+        verifier.reload_if_changed().unwrap();
+        assert_eq!(verifier.client_auth_root_subjects().len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
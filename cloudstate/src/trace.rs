@@ -0,0 +1,251 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tonic::metadata::MetadataMap;
+use tracing::Span;
+use tracing::field::display;
+
+/// W3C Trace Context header, `{version}-{trace id}-{parent span id}-{flags}`.
+pub const TRACEPARENT: &str = "traceparent";
+/// Single B3 header, `{trace id}-{span id}-{sampled}`.
+pub const B3: &str = "b3";
+pub const B3_TRACE_ID: &str = "x-b3-traceid";
+pub const B3_SPAN_ID: &str = "x-b3-spanid";
+pub const B3_SAMPLED: &str = "x-b3-sampled";
+pub const B3_FLAGS: &str = "x-b3-flags";
+
+/// Trace context of a call, taken from the gRPC metadata of the stream carrying the commands.
+///
+/// Every command is handled in a span that is a child of the incoming context.
+/// The context is not propagated to forwards and side effects: `Forward`, `SideEffect` and
+/// `Command` of the protocol carry no metadata, the proxy would drop any header set on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+    pub sampled: bool,
+}
+
+impl TraceContext {
+
+    /// Extracts the context of a gRPC request, preferring `traceparent` over the B3 headers.
+    pub fn from_headers(headers: &MetadataMap) -> Option<TraceContext> {
+        extract(|key| headers.get(key).and_then(|value| value.to_str().ok()))
+    }
+
+    pub fn parse_traceparent(value: &str) -> Option<TraceContext> {
+        let parts: Vec<&str> = value.trim().split('-').collect();
+        // Later versions may append fields, version ff is forbidden
+        let valid = match parts.first() {
+            Some(&"00") => parts.len() == 4,
+            Some(&"ff") => false,
+            Some(version) => version.len() == 2 && parts.len() >= 4,
+            None => false,
+        };
+        if !valid || parts[1].len() != 32 || parts[2].len() != 16 || parts[3].len() != 2 {
+            return None;
+        }
+
+        let flags = u8::from_str_radix(parts[3], 16).ok()?;
+        context(parts[1], parts[2], flags & 1 == 1)
+    }
+
+    /// Parses the single B3 header. A header holding only the sampling decision carries no context.
+    pub fn parse_b3(value: &str) -> Option<TraceContext> {
+        let parts: Vec<&str> = value.trim().split('-').collect();
+        if parts.len() < 2 {
+            return None;
+        }
+
+        let sampled = match parts.get(2) {
+            None | Some(&"1") | Some(&"d") => true,
+            Some(&"0") => false,
+            Some(_) => return None,
+        };
+        context(parts[0], parts[1], sampled)
+    }
+
+    /// Makes `span` a child of this context and returns the context of `span`.
+    ///
+    /// The span must declare the empty fields `trace_id`, `span_id` and `parent_id`.
+    pub fn enter(&self, span: &Span) -> TraceContext {
+        let context = self.span_context(span);
+        span.record("trace_id", &display(format!("{:032x}", context.trace_id)));
+        span.record("span_id", &display(format!("{:016x}", context.span_id)));
+        span.record("parent_id", &display(format!("{:016x}", self.span_id)));
+        context
+    }
+
+    /// Context of a new span in the same trace.
+    fn child(&self) -> TraceContext {
+        TraceContext {
+            span_id: new_span_id(),
+            ..*self
+        }
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    fn span_context(&self, _span: &Span) -> TraceContext {
+        self.child()
+    }
+
+    /// Links the span exported over OTLP to the remote parent, so both share their ids.
+    #[cfg(feature = "otlp")]
+    fn span_context(&self, span: &Span) -> TraceContext {
+        match otlp::set_parent(span, self) {
+            0 => self.child(),
+            span_id => TraceContext { span_id, ..*self },
+        }
+    }
+}
+
+fn extract<'a>(get: impl Fn(&str) -> Option<&'a str>) -> Option<TraceContext> {
+    if let Some(context) = get(TRACEPARENT).and_then(TraceContext::parse_traceparent) {
+        return Some(context);
+    }
+    if let Some(context) = get(B3).and_then(TraceContext::parse_b3) {
+        return Some(context);
+    }
+
+    let trace_id = get(B3_TRACE_ID)?;
+    let span_id = get(B3_SPAN_ID)?;
+    let sampled = get(B3_FLAGS) == Some("1")
+        || get(B3_SAMPLED).map_or(true, |sampled| sampled == "1" || sampled.eq_ignore_ascii_case("true"));
+    context(trace_id, span_id, sampled)
+}
+
+// B3 allows 64 bit trace ids, all zero ids are invalid in both formats
+fn context(trace_id: &str, span_id: &str, sampled: bool) -> Option<TraceContext> {
+    if (trace_id.len() != 16 && trace_id.len() != 32) || span_id.len() != 16 {
+        return None;
+    }
+
+    let trace_id = u128::from_str_radix(trace_id, 16).ok()?;
+    let span_id = u64::from_str_radix(span_id, 16).ok()?;
+    if trace_id == 0 || span_id == 0 {
+        return None;
+    }
+    Some(TraceContext { trace_id, span_id, sampled })
+}
+
+fn new_span_id() -> u64 {
+    static SPANS: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(SPANS.fetch_add(1, Ordering::Relaxed));
+    match hasher.finish() {
+        0 => 1,
+        span_id => span_id,
+    }
+}
+
+#[cfg(feature = "otlp")]
+pub use self::otlp::layer as otlp_layer;
+
+/// Export of the spans over OTLP, through tracing-opentelemetry.
+#[cfg(feature = "otlp")]
+mod otlp {
+    use opentelemetry::Context;
+    use opentelemetry::sdk::trace::Tracer;
+    use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceId, TraceState};
+    use opentelemetry_otlp::Uninstall;
+    use tracing::{Span, Subscriber};
+    use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+    use tracing_subscriber::registry::LookupSpan;
+    use crate::error::Error;
+    use super::TraceContext;
+
+    /// Layer exporting the spans to the OTLP collector at `endpoint`, e.g. `http://localhost:4317`.
+    /// The export stops once the returned guard is dropped.
+    pub fn layer<S>(endpoint: &str) -> Result<(OpenTelemetryLayer<S, Tracer>, Uninstall), Error>
+        where S: Subscriber + for<'span> LookupSpan<'span> {
+        let (tracer, uninstall) = opentelemetry_otlp::new_pipeline()
+            .with_endpoint(endpoint)
+            .install()
+            .map_err(|err| Error::Config(format!("Unable to export the spans to {}: {}", endpoint, err)))?;
+        Ok((tracing_opentelemetry::layer().with_tracer(tracer), uninstall))
+    }
+
+    /// Returns the id of the exported span, or 0 when no OTLP layer is installed.
+    pub fn set_parent(span: &Span, parent: &TraceContext) -> u64 {
+        let remote = SpanContext::new(
+            TraceId::from_u128(parent.trace_id),
+            SpanId::from_u64(parent.span_id),
+            parent.sampled as u8,
+            true,
+            TraceState::default(),
+        );
+        span.set_parent(&Context::new().with_remote_span_context(remote));
+        span.context().span().span_context().span_id().to_u64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TraceContext;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const SPAN_ID: &str = "00f067aa0ba902b7";
+
+    fn context(sampled: bool) -> TraceContext {
+        TraceContext {
+            trace_id: 0x4bf92f3577b34da6a3ce929d0e0e4736,
+            span_id: 0x00f067aa0ba902b7,
+            sampled,
+        }
+    }
+
+    #[test]
+    fn parse_traceparent() {
+        let sampled = format!("00-{}-{}-01", TRACE_ID, SPAN_ID);
+        assert_eq!(TraceContext::parse_traceparent(&sampled), Some(context(true)));
+        assert_eq!(TraceContext::parse_traceparent(&format!("00-{}-{}-00", TRACE_ID, SPAN_ID)), Some(context(false)));
+
+        // Later versions may append fields
+        assert_eq!(TraceContext::parse_traceparent(&format!("01-{}-{}-01-extra", TRACE_ID, SPAN_ID)), Some(context(true)));
+    }
+
+    #[test]
+    fn parse_traceparent_rejects_invalid_values() {
+        let invalid = [
+            String::new(),
+            format!("ff-{}-{}-01", TRACE_ID, SPAN_ID),
+            format!("00-{}-{}-01-extra", TRACE_ID, SPAN_ID),
+            format!("00-{}-{}-01", "0".repeat(32), SPAN_ID),
+            format!("00-{}-{}-01", TRACE_ID, "0".repeat(16)),
+            format!("00-{}-{}-01", &TRACE_ID[..16], SPAN_ID),
+            format!("00-{}-{}-zz", TRACE_ID, SPAN_ID),
+            format!("00-{}-{}-01", TRACE_ID.replace('a', "g"), SPAN_ID),
+        ];
+        for value in invalid.iter() {
+            assert_eq!(TraceContext::parse_traceparent(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn parse_b3() {
+        assert_eq!(TraceContext::parse_b3(&format!("{}-{}-1", TRACE_ID, SPAN_ID)), Some(context(true)));
+        assert_eq!(TraceContext::parse_b3(&format!("{}-{}-d", TRACE_ID, SPAN_ID)), Some(context(true)));
+        assert_eq!(TraceContext::parse_b3(&format!("{}-{}-0", TRACE_ID, SPAN_ID)), Some(context(false)));
+        // Sampling is deferred without a decision
+        assert_eq!(TraceContext::parse_b3(&format!("{}-{}", TRACE_ID, SPAN_ID)), Some(context(true)));
+
+        let short = TraceContext::parse_b3(&format!("a3ce929d0e0e4736-{}", SPAN_ID)).unwrap();
+        assert_eq!(short.trace_id, 0xa3ce929d0e0e4736);
+    }
+
+    #[test]
+    fn parse_b3_rejects_invalid_values() {
+        let invalid = [
+            String::from("1"),
+            String::from("0"),
+            format!("{}-{}-2", TRACE_ID, SPAN_ID),
+            format!("{}-{}-1", "0".repeat(32), SPAN_ID),
+            format!("{}-{}-1", TRACE_ID, "0".repeat(16)),
+            format!("{}-{}-1", &TRACE_ID[..20], SPAN_ID),
+            format!("{}-{}-1", TRACE_ID, SPAN_ID.replace('a', "x")),
+        ];
+        for value in invalid.iter() {
+            assert_eq!(TraceContext::parse_b3(value), None, "{:?}", value);
+        }
+    }
+}